@@ -0,0 +1,145 @@
+//! Fits the Multi-ProbCut parameters from self-play games
+//!
+//! Every position of every game is searched to each depth without
+//! Multi-ProbCut, then the deep scores are fitted against the shallow
+//! scores for every depth, check and phase with least squares. The games
+//! are scored with the default Heuristic, and the fitted table is printed
+//! as Rust, ready to replace `HEURISTIC_CUTS` in `src/bot/probcut.rs`.
+//!
+//! Usage: cargo run --release --bin fit_probcut -- [--games N] [--seed N]
+
+use othellotron::bot::{analyze, Config, Heuristic, Limit, Random, TranspositionTable};
+use othellotron::bot::probcut::{shallow_depths, phase, CHECKS, PHASES, MAX_DEPTH};
+use othellotron::game::{Board, Move, play_move};
+
+// Scores past this are wins or losses, not estimates, and would wreck the fit
+const MAX_SCORE: i32 = 100_000;
+
+// Fits of fewer samples than this are left out, they are more noise than fit
+const MIN_SAMPLES: f64 = 30.0;
+
+// One in this many moves of a game is random instead of the bot's move, so games differ
+const RANDOM_MOVE_ODDS: usize = 4;
+
+/// Running sums for a least squares fit of deep = a * shallow + b
+#[derive(Clone, Copy, Default)]
+struct Fit {
+    n: f64,
+    x: f64,
+    y: f64,
+    xx: f64,
+    xy: f64,
+    yy: f64
+}
+
+impl Fit {
+    fn add(&mut self, shallow: i32, deep: i32) {
+        let (x, y) = (shallow as f64, deep as f64);
+        self.n += 1.0;
+        self.x += x;
+        self.y += y;
+        self.xx += x * x;
+        self.xy += x * y;
+        self.yy += y * y;
+    }
+
+    /// Returns slope, offset and deviation of the error, None without enough samples
+    fn solve(&self) -> Option<(f64, f64, f64)> {
+        let variance = self.n * self.xx - self.x * self.x;
+        if self.n < MIN_SAMPLES || variance <= 0.0 {
+            return None
+        }
+
+        let a = (self.n * self.xy - self.x * self.y) / variance;
+        let b = (self.y - a * self.x) / self.n;
+
+        // Sum of squared errors, expanded so that samples don't need to be kept
+        let error = self.yy - 2.0 * a * self.xy - 2.0 * b * self.y + a * a * self.xx + 2.0 * a * b * self.x + b * b * self.n;
+        Some((a, b, (error.max(0.0) / self.n).sqrt()))
+    }
+}
+
+fn main() {
+    let mut games = 20;
+    let mut seed = 1;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value: Option<u64> = args.next().and_then(|value| value.parse().ok());
+        match (arg.as_str(), value) {
+            ("--games", Some(value)) => games = value,
+            ("--seed", Some(value)) => seed = value,
+            _ => {
+                eprintln!("Usage: fit_probcut [--games N] [--seed N]");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let mut random = Random::new(seed);
+    let mut fits = [[[Fit::default(); PHASES]; CHECKS]; MAX_DEPTH as usize + 1];
+
+    for game in 0..games {
+        let mut board = Board::new();
+
+        while let Some(random_move) = random.pick_move(&board) {
+            if random_move == Move::Pass {
+                play_move(&mut board, Move::Pass);
+                continue
+            }
+
+            // Score of every depth, searched in full
+            let color = board.to_move;
+            let mut scores = vec![0];
+            let mut best = random_move;
+            for depth in 1..=MAX_DEPTH {
+                let config = Config { limit: Limit::Depth(depth), probcut: false, ..Config::default() };
+                // A fresh table for every depth, so shallow scores aren't helped by deeper searches
+                let tt = TranspositionTable::default();
+                let analysis = analyze(&board, color, &config, &Heuristic::default(), &tt).unwrap(); // Moves were checked above
+
+                scores.push(analysis.score);
+                best = analysis.pv[0];
+            }
+
+            let phase = phase(board.empty().count_ones());
+            for depth in 0..=MAX_DEPTH {
+                for (check, shallow) in shallow_depths(depth).into_iter().enumerate() {
+                    let Some(shallow) = shallow else { continue };
+                    let (shallow, deep) = (scores[shallow as usize], scores[depth as usize]);
+
+                    if shallow.abs() < MAX_SCORE && deep.abs() < MAX_SCORE {
+                        fits[depth as usize][check][phase].add(shallow, deep);
+                    }
+                }
+            }
+
+            let m = if random.below(RANDOM_MOVE_ODDS) == 0 { random_move } else { best };
+            play_move(&mut board, m);
+        }
+
+        eprintln!("Game {} of {games} done", game + 1);
+    }
+
+    // Checks a depth doesn't have never get samples, so they come out as 0 too
+    println!("pub const HEURISTIC_CUTS: Cuts = [");
+    for (depth, fits) in fits.iter().enumerate() {
+        println!("    [ // Depth {depth}");
+        for (check, fits) in fits.iter().enumerate() {
+            let cuts: Vec<String> = fits
+                .iter()
+                .map(|fit| match fit.solve() {
+                    Some((a, b, sigma)) => format!("Cut::new({a:.3}, {b:.1}, {sigma:.1})"),
+                    None => String::from("Cut::new(0.0, 0.0, 0.0)")
+                })
+                .collect();
+
+            let end = if check == CHECKS - 1 { "" } else { "," };
+            println!("        [{}]{end}", cuts.join(", "));
+        }
+
+        let end = if depth as u32 == MAX_DEPTH { "" } else { "," };
+        println!("    ]{end}");
+    }
+    println!("];");
+}
@@ -0,0 +1,278 @@
+//! Tunes the pattern weights from labeled positions
+//!
+//! A labeled position is a board with the final disc difference of the
+//! game it was played in, from the side to move. The weights are fitted
+//! with gradient descent so that the evaluation of each position comes as
+//! close as it can to its final score (least squares), and written to a
+//! file the engine loads with `--weights`.
+//!
+//! Only the pattern evaluation is tuned. The hand written evaluation
+//! (`Heuristic`, its `Weights` and `WEIGHT_LOOKUP`) is set by hand and left
+//! as it is.
+//!
+//! Positions are read from files with one position per line, written like
+//! `--position` followed by the score and with an empty line between games,
+//! from games saved with `save` (every position is labeled with how the
+//! game ended), or from games the bot plays against itself. Whole games are
+//! kept out of the fit for validation, as positions of the same game are
+//! too much alike to say how the weights do on games they haven't seen.
+//!
+//! Usage: cargo run --release --bin tune -- [--positions FILE] [--games FILE]
+//!        [--self-play N] [--save-positions FILE] [--weights FILE] [--out FILE]
+//!        [--epochs N] [--rate R] [--seed N]
+
+use std::fs;
+
+use othellotron::bot::{choose_move_with, Config, Limit, PatternEvaluator, Random, TranspositionTable};
+use othellotron::bot::pattern::{features, stage, FEATURES, DISC_SCALE};
+use othellotron::bot::moves::generate_moves;
+use othellotron::game::{Board, Move, play_move, validate_move, parse_notation};
+
+const USAGE: &str = "Usage: tune [--positions FILE] [--games FILE] [--self-play N] [--save-positions FILE] \
+[--weights FILE] [--out FILE] [--epochs N] [--rate R] [--seed N]";
+
+// Depth the bot searches to in self-play games, deep enough to play sensibly and cheap enough for many games
+const SELF_PLAY_DEPTH: u32 = 4;
+
+// One in this many moves of a self-play game is random instead of the bot's move, so games differ
+const RANDOM_MOVE_ODDS: usize = 4;
+
+// One in this many games is kept out of the fit, to see how well the weights do on games they haven't seen
+const VALIDATION_ODDS: usize = 10;
+
+/// Board with the final disc difference of its game, from the side to move
+#[derive(Clone, Copy)]
+struct Sample {
+    board: Board,
+    score: i32
+}
+
+fn main() {
+    let mut positions = vec![];
+    let mut games = vec![];
+    let mut self_play = 0;
+    let mut save_positions = None;
+    let mut weights = None;
+    let mut out = String::from("weights.bin");
+    let mut epochs = 100;
+    let mut rate = 1.0;
+    let mut seed = 1;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let Some(value) = args.next() else { usage() };
+
+        match arg.as_str() {
+            "--positions" => positions.push(value),
+            "--games" => games.push(value),
+            "--self-play" => self_play = value.parse().unwrap_or_else(|_| usage()),
+            "--save-positions" => save_positions = Some(value),
+            "--weights" => weights = Some(value),
+            "--out" => out = value,
+            "--epochs" => epochs = value.parse().unwrap_or_else(|_| usage()),
+            "--rate" => rate = value.parse().ok().filter(|&rate: &f32| rate > 0.0).unwrap_or_else(|| usage()),
+            "--seed" => seed = value.parse().unwrap_or_else(|_| usage()),
+            _ => usage()
+        }
+    }
+
+    // Tuning carries on from the given weights, otherwise from the seeded ones
+    let evaluator = match &weights {
+        Some(file) => PatternEvaluator::load(file).unwrap_or_else(|e| fail(&format!("Could not load weights from {file}: {e}"))),
+        None => PatternEvaluator::new()
+    };
+
+    // Positions are kept by game, so that games are either tuned on or validated on as a whole
+    let mut games_played: Vec<Vec<Sample>> = vec![];
+    for file in &positions {
+        let text = fs::read_to_string(file).unwrap_or_else(|e| fail(&format!("Could not read {file}: {e}")));
+        games_played.extend(read_positions(&text).unwrap_or_else(|e| fail(&format!("{file}: {e}"))));
+    }
+    for file in &games {
+        let text = fs::read_to_string(file).unwrap_or_else(|e| fail(&format!("Could not read {file}: {e}")));
+        games_played.push(read_game(&text).unwrap_or_else(|e| fail(&format!("{file}: {e}"))));
+    }
+
+    let mut random = Random::new(seed);
+    for game in 0..self_play {
+        games_played.push(play_game(&evaluator, &mut random));
+        eprintln!("Game {} of {self_play} done", game + 1);
+    }
+
+    // The evaluation knows the score once the game is over, these teach it nothing
+    for samples in &mut games_played {
+        samples.retain(|sample| !game_is_over(&sample.board));
+    }
+    games_played.retain(|samples| !samples.is_empty());
+
+    if let Some(file) = &save_positions {
+        let text: Vec<String> = games_played
+            .iter()
+            .map(|samples| samples.iter().map(|sample| format!("{} {}\n", sample.board.position(), sample.score)).collect())
+            .collect();
+        fs::write(file, text.join("\n")).unwrap_or_else(|e| fail(&format!("Could not save positions to {file}: {e}")));
+    }
+
+    if games_played.is_empty() {
+        fail("No positions to tune on, give --positions, --games or --self-play");
+    }
+
+    let (training, validation): (Vec<_>, Vec<_>) = games_played
+        .into_iter()
+        .enumerate()
+        .partition(|(n, _)| n % VALIDATION_ODDS != VALIDATION_ODDS - 1);
+    let training: Vec<Sample> = training.into_iter().flat_map(|(_, samples)| samples).collect();
+    let validation: Vec<Sample> = validation.into_iter().flat_map(|(_, samples)| samples).collect();
+    eprintln!("Tuning on {} positions, validating on {}", training.len(), validation.len());
+
+    let mut weights: Vec<f32> = evaluator.weights().iter().map(|&weight| weight as f32).collect();
+    let instances = features(&Board::new(), true).count() as f32;
+
+    for epoch in 1..=epochs {
+        let mut gradient = vec![0.0; weights.len()];
+        let mut counts = vec![0u32; weights.len()];
+
+        for sample in &training {
+            let offset = stage(sample.board.empty().count_ones()) * FEATURES;
+            let residual = sample.score as f32 * DISC_SCALE as f32 - predict(&weights, sample);
+
+            for feature in features(&sample.board, sample.board.to_move) {
+                gradient[offset + feature] += residual;
+                counts[offset + feature] += 1;
+            }
+        }
+
+        // Every weight moves by its average error, shared between the patterns of a position.
+        // Weights no position looks up keep their value
+        for ((weight, gradient), count) in weights.iter_mut().zip(gradient).zip(counts) {
+            if count > 0 {
+                *weight += rate * gradient / count as f32 / instances;
+            }
+        }
+
+        eprintln!(
+            "Epoch {epoch} of {epochs}: error {:.2} discs, validation error {:.2} discs",
+            error(&weights, &training),
+            error(&weights, &validation)
+        );
+    }
+
+    let weights = weights.iter().map(|&weight| weight.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16).collect();
+    PatternEvaluator::from_weights(weights)
+        .unwrap() // Same number of weights it started with
+        .save(&out)
+        .unwrap_or_else(|e| fail(&format!("Could not save weights to {out}: {e}")));
+    println!("Saved weights to {out}");
+}
+
+/// Evaluation of a sample with the weights being tuned
+fn predict(weights: &[f32], sample: &Sample) -> f32 {
+    let offset = stage(sample.board.empty().count_ones()) * FEATURES;
+    features(&sample.board, sample.board.to_move).map(|feature| weights[offset + feature]).sum()
+}
+
+/// Root mean squared error in discs, 0 without samples
+fn error(weights: &[f32], samples: &[Sample]) -> f64 {
+    let squares: f64 = samples
+        .iter()
+        .map(|sample| (sample.score as f64 - predict(weights, sample) as f64 / DISC_SCALE as f64).powi(2))
+        .sum();
+
+    (squares / samples.len().max(1) as f64).sqrt()
+}
+
+/// Reads one position per line followed by its score, an empty line starts the next game
+fn read_positions(text: &str) -> Result<Vec<Vec<Sample>>, String> {
+    let mut games = vec![vec![]];
+
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            games.push(vec![]);
+            continue
+        }
+
+        let (position, score) = line.trim().rsplit_once(char::is_whitespace).ok_or(format!("line {} has no score", n + 1))?;
+        let board = position.parse().map_err(|e| format!("line {}: {e}", n + 1))?;
+        let score = score.parse().map_err(|_| format!("line {}: \"{score}\" is not a score", n + 1))?;
+
+        games.last_mut().unwrap().push(Sample { board, score }); // Starts with one game
+    }
+
+    games.retain(|game| !game.is_empty());
+    Ok(games)
+}
+
+/// Plays out a game saved with save, every position is labeled with the final score
+fn read_game(text: &str) -> Result<Vec<Sample>, String> {
+    let mut words = text.split_whitespace().peekable();
+    let mut board = match words.peek().and_then(|word| word.parse::<Board>().ok()) {
+        Some(start) => {
+            words.next();
+            start
+        },
+        None => Board::new()
+    };
+
+    let mut boards = vec![];
+    for (n, word) in words.enumerate() {
+        let m = if word.eq_ignore_ascii_case("pass") {
+            Move::Pass
+        } else {
+            Move::Play(parse_notation(word).ok_or(format!("move {} \"{word}\" is not a tile", n + 1))?)
+        };
+
+        if !validate_move(&board, m) {
+            return Err(format!("move {} \"{word}\" is not valid", n + 1))
+        }
+
+        boards.push(board);
+        play_move(&mut board, m);
+    }
+
+    if !game_is_over(&board) {
+        return Err(String::from("game is not finished, so there is no final score"))
+    }
+
+    Ok(label(boards, &board))
+}
+
+/// Plays a game of the bot against itself from the starting position
+fn play_game(evaluator: &PatternEvaluator, random: &mut Random) -> Vec<Sample> {
+    let config = Config { limit: Limit::Depth(SELF_PLAY_DEPTH), ..Config::default() };
+    let tt = TranspositionTable::default();
+    let mut board = Board::new();
+    let mut boards = vec![];
+
+    while let Some(m) = choose_move_with(&board, board.to_move, &config, evaluator, &tt) {
+        let m = if random.below(RANDOM_MOVE_ODDS) == 0 { random.pick_move(&board).unwrap_or(m) } else { m };
+
+        boards.push(board);
+        play_move(&mut board, m);
+    }
+
+    label(boards, &board)
+}
+
+/// Labels every board of a game with the final disc difference from its side to move
+fn label(boards: Vec<Board>, end: &Board) -> Vec<Sample> {
+    let white = end.piece_count(true) as i32 - end.piece_count(false) as i32;
+
+    boards
+        .into_iter()
+        .map(|board| Sample { board, score: if board.to_move { white } else { -white } })
+        .collect()
+}
+
+fn game_is_over(board: &Board) -> bool {
+    generate_moves(board, true).is_empty() && generate_moves(board, false).is_empty()
+}
+
+fn usage() -> ! {
+    eprintln!("{USAGE}");
+    std::process::exit(2);
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}");
+    std::process::exit(1);
+}
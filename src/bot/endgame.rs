@@ -0,0 +1,386 @@
+use std::time::Instant;
+
+use crate::game::{Board, Bits, bitboard_flips};
+use super::moves::{bitboard_moves, replies};
+use super::stability::stable_discs;
+
+/// Default number of empty tiles at which the bot stops
+/// evaluating and starts solving the game to the end
+pub const DEFAULT_ENDGAME_EMPTIES: u32 = 14;
+
+/// Default number of empty tiles at which the bot starts
+/// solving for win, loss or draw, before exact solving
+pub const DEFAULT_WLD_EMPTIES: u32 = 16;
+
+// Below this many empties, counting replies for every move costs more than it saves
+const FASTEST_FIRST_EMPTIES: u32 = 7;
+
+// How many nodes are solved between checks of the clock
+const NODES_PER_TIME_CHECK: u64 = 1024;
+
+// The 4 corner regions of the board, used for parity
+const QUADRANTS: [u64; 4] = [
+    0x000000000f0f0f0f, // A8 - D5
+    0x00000000f0f0f0f0, // E8 - H5
+    0x0f0f0f0f00000000, // A4 - D1
+    0xf0f0f0f000000000  // E4 - H1
+];
+
+/// Exact endgame solver. Searches every line to the end of the game,
+/// so the score is the real final disc difference with perfect play
+/// from both sides rather than an estimate. Only practical once few
+/// tiles are left empty.
+///
+/// # Arguments
+/// *   board: Board which is solved
+/// *   color: Side whose moves are being solved
+///
+/// Returns final disc difference for color and best move, None if color has to pass
+pub fn solve(board: &Board, color: bool) -> (i32, Option<usize>) {
+    solve_until(board, color, None).unwrap() // Never stopped without a deadline
+}
+
+/// solve function that gives up once a deadline has passed
+///
+/// # Arguments
+/// *   board: Board which is solved
+/// *   color: Side whose moves are being solved
+/// *   deadline: Time at which solving is abandoned, None to always finish
+///
+/// Returns final disc difference for color and best move, None if time ran out
+pub fn solve_until(board: &Board, color: bool, deadline: Option<Instant>) -> Option<(i32, Option<usize>)> {
+    let mut solver = Solver::new(deadline);
+    let player = board.bitboard(color);
+    let opponent = board.bitboard(!color);
+    let moves = bitboard_moves(player, opponent);
+
+    if moves == 0 {
+        let score = solver.solve_inner(player, opponent, -64, 64);
+        return (!solver.stopped).then_some((score, None))
+    }
+
+    let mut alpha = -64;
+    let mut best = (-65, None);
+    for m in ordered_moves(player, opponent, moves) {
+        let score = -solver.solve_move(player, opponent, m, -64, -alpha);
+
+        if score > best.0 {
+            best = (score, Some(m));
+            alpha = alpha.max(score);
+        }
+    }
+
+    (!solver.stopped).then_some(best)
+}
+
+/// solve function but the exact score of every move is found, not just
+/// the best one. Slower, as no move can be cut off by a better sibling
+///
+/// # Arguments
+/// *   board: Board which is solved
+/// *   color: Side whose moves are being solved
+///
+/// Returns vector of final disc differences and moves
+pub fn solve_moves(board: &Board, color: bool) -> Vec<(i32, usize)> {
+    let mut solver = Solver::new(None);
+    let player = board.bitboard(color);
+    let opponent = board.bitboard(!color);
+
+    ordered_moves(player, opponent, bitboard_moves(player, opponent))
+        .into_iter()
+        .map(|m| (-solver.solve_move(player, opponent, m, -64, 64), m))
+        .collect()
+}
+
+/// Final result of a game for one side
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Outcome {
+    /// Side finishes with fewer pieces
+    Loss,
+    /// Both sides finish with the same number of pieces
+    Draw,
+    /// Side finishes with more pieces
+    Win
+}
+
+/// Win/Loss/Draw solver. Same search as the exact solver, but with a
+/// window of -1 to 1 around a draw. Any line that is known to win by at
+/// least 1 or lose by at least 1 gets cut off without finding by how
+/// much, which makes it a lot cheaper than finding the exact score.
+///
+/// # Arguments
+/// *   board: Board which is solved
+/// *   color: Side whose moves are being solved
+///
+/// Returns vector of outcomes and moves
+pub fn solve_wld(board: &Board, color: bool) -> Vec<(Outcome, usize)> {
+    solve_wld_until(board, color, None).unwrap() // Never stopped without a deadline
+}
+
+/// solve_wld function that gives up once a deadline has passed
+///
+/// # Arguments
+/// *   board: Board which is solved
+/// *   color: Side whose moves are being solved
+/// *   deadline: Time at which solving is abandoned, None to always finish
+///
+/// Returns vector of outcomes and moves, None if time ran out
+pub fn solve_wld_until(board: &Board, color: bool, deadline: Option<Instant>) -> Option<Vec<(Outcome, usize)>> {
+    let mut solver = Solver::new(deadline);
+    let player = board.bitboard(color);
+    let opponent = board.bitboard(!color);
+
+    let outcomes = ordered_moves(player, opponent, bitboard_moves(player, opponent))
+        .into_iter()
+        .map(|m| {
+            let outcome = match (-solver.solve_move(player, opponent, m, -1, 1)).signum() {
+                1 => Outcome::Win,
+                0 => Outcome::Draw,
+                _ => Outcome::Loss
+            };
+            (outcome, m)
+        })
+        .collect();
+
+    (!solver.stopped).then_some(outcomes)
+}
+
+/// State carried through every node of a single solve
+struct Solver {
+    deadline: Option<Instant>, // Solving is abandoned once this is passed
+    stopped: bool,
+    nodes: u64
+}
+
+impl Solver {
+    fn new(deadline: Option<Instant>) -> Self {
+        Self { deadline, stopped: false, nodes: 0 }
+    }
+
+    /// Checks the clock every so often, once time is up every node
+    /// returns straight away and the result is thrown out
+    fn out_of_time(&mut self) -> bool {
+        self.nodes += 1;
+
+        if !self.stopped && self.nodes.is_multiple_of(NODES_PER_TIME_CHECK) {
+            self.stopped = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        }
+
+        self.stopped
+    }
+
+    /// Plays a move on the bitmaps and solves the position for the opponent
+    fn solve_move(&mut self, player: u64, opponent: u64, m: usize, alpha: i32, beta: i32) -> i32 {
+        let flips = bitboard_flips(player, opponent, m);
+        self.solve_inner(opponent & !flips, player | flips | 1 << m, alpha, beta)
+    }
+
+    /// Alpha-beta search to the end of the game. Works on the bitmaps of both
+    /// sides directly, no move is ever undone as each node gets its own copy
+    ///
+    /// # Arguments
+    /// *   player: Bitmap of side to move
+    /// *   opponent: Bitmap of other side
+    /// *   alpha: Best score for side to move
+    /// *   beta: Best score for other side
+    ///
+    /// Returns final disc difference for side to move, or a bound of it outside of alpha and beta
+    fn solve_inner(&mut self, player: u64, opponent: u64, mut alpha: i32, beta: i32) -> i32 {
+        if self.out_of_time() {
+            return 0 // Thrown out by the caller
+        }
+
+        // The opponent keeps its stable discs whatever is played, so the score can't be above
+        // 64 minus twice them. Only worth finding them when the opponent has enough discs for that to cut
+        if alpha >= 64 - 2 * opponent.count_ones() as i32 {
+            let bound = 64 - 2 * stable_discs(opponent, player).count_ones() as i32;
+            if bound <= alpha {
+                return bound
+            }
+        }
+
+        let moves = bitboard_moves(player, opponent);
+
+        if moves == 0 {
+            if bitboard_moves(opponent, player) == 0 { // Game Over
+                return player.count_ones() as i32 - opponent.count_ones() as i32
+            }
+
+            return -self.solve_inner(opponent, player, -beta, -alpha) // Pass
+        }
+
+        let mut best = -65;
+        for m in ordered_moves(player, opponent, moves) {
+            let score = -self.solve_move(player, opponent, m, -beta, -alpha);
+
+            if score > best {
+                best = score;
+                alpha = alpha.max(score);
+
+                if alpha >= beta {
+                    break // Opponent will never allow this line
+                }
+            }
+        }
+
+        best
+    }
+}
+
+/// Orders moves for the endgame. Moves into regions with an odd number
+/// of empty tiles go first, as the side that moves last in a region
+/// tends to keep what it takes (parity). With enough empties left, the
+/// moves that leave the opponent with the fewest replies go before that
+/// (fastest-first), as they keep the tree narrow.
+///
+/// # Arguments
+/// *   player: Bitmap of side to move
+/// *   opponent: Bitmap of other side
+/// *   moves: Bitmap of valid moves
+fn ordered_moves(player: u64, opponent: u64, moves: u64) -> Vec<usize> {
+    let empty = !(player | opponent);
+    let odd = QUADRANTS
+        .into_iter()
+        .filter(|&quadrant| (quadrant & empty).count_ones() % 2 == 1)
+        .fold(0, |acc, quadrant| acc | quadrant);
+
+    let mut moves: Vec<usize> = Bits(moves).collect();
+
+    if empty.count_ones() > FASTEST_FIRST_EMPTIES {
+        moves.sort_by_cached_key(|&m| (replies(player, opponent, m), odd >> m & 1 == 0));
+    } else {
+        moves.sort_by_key(|&m| odd >> m & 1 == 0);
+    }
+
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Random;
+    use crate::bot::moves::move_mask;
+
+    /// Plain minimax to the end of the game, nothing is cut off
+    fn minimax(player: u64, opponent: u64) -> i32 {
+        let moves = bitboard_moves(player, opponent);
+
+        if moves == 0 {
+            if bitboard_moves(opponent, player) == 0 {
+                return player.count_ones() as i32 - opponent.count_ones() as i32
+            }
+            return -minimax(opponent, player)
+        }
+
+        Bits(moves)
+            .map(|m| {
+                let flips = bitboard_flips(player, opponent, m);
+                -minimax(opponent & !flips, player | flips | 1 << m)
+            })
+            .max()
+            .unwrap() // There is a move
+    }
+
+    /// Random games played until a position with empties left that
+    /// passes the filter, every call gives a new position
+    fn random_position(random: &mut Random, empties: u32, filter: impl Fn(&Board) -> bool) -> Board {
+        loop {
+            let mut board = Board::new();
+
+            while board.empty().count_ones() > empties && random.play_move(&mut board).is_some() {}
+
+            let over = move_mask(&board, true) | move_mask(&board, false) == 0;
+            if board.empty().count_ones() == empties && !over && filter(&board) {
+                return board
+            }
+        }
+    }
+
+    /// Positions with 9 to 11 empties, the last one has the side to move pass
+    fn positions() -> Vec<Board> {
+        let mut random = Random::new(1);
+        let mut positions: Vec<Board> = [9, 9, 10, 10, 11].into_iter().map(|empties| random_position(&mut random, empties, |_| true)).collect();
+        positions.push(random_position(&mut random, 10, |board| move_mask(board, board.to_move) == 0));
+        positions
+    }
+
+    /// Exact score of every move for the side to move, found by minimax
+    fn move_scores(board: &Board) -> Vec<(i32, usize)> {
+        let (player, opponent) = (board.bitboard(board.to_move), board.bitboard(!board.to_move));
+
+        Bits(bitboard_moves(player, opponent))
+            .map(|m| {
+                let flips = bitboard_flips(player, opponent, m);
+                (-minimax(opponent & !flips, player | flips | 1 << m), m)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn solve_matches_minimax() {
+        for board in positions() {
+            let color = board.to_move;
+            let scores = move_scores(&board);
+            let (score, m) = solve(&board, color);
+
+            match m {
+                Some(m) => {
+                    let best = scores.iter().map(|&(score, _)| score).max();
+                    assert_eq!(Some(score), best, "{}", board.position());
+                    assert!(scores.contains(&(score, m)), "{}", board.position());
+                },
+                None => {
+                    assert!(scores.is_empty(), "{}", board.position());
+                    assert_eq!(score, minimax(board.bitboard(color), board.bitboard(!color)), "{}", board.position());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn solve_moves_matches_minimax() {
+        for board in positions() {
+            let mut scores = solve_moves(&board, board.to_move);
+            let mut expected = move_scores(&board);
+            scores.sort_unstable_by_key(|&(_, m)| m);
+            expected.sort_unstable_by_key(|&(_, m)| m);
+
+            assert_eq!(scores, expected, "{}", board.position());
+        }
+    }
+
+    #[test]
+    fn solve_wld_matches_minimax() {
+        for board in positions() {
+            let mut outcomes = solve_wld(&board, board.to_move);
+            let mut expected: Vec<(Outcome, usize)> = move_scores(&board)
+                .into_iter()
+                .map(|(score, m)| (match score.signum() {
+                    1 => Outcome::Win,
+                    0 => Outcome::Draw,
+                    _ => Outcome::Loss
+                }, m))
+                .collect();
+            outcomes.sort_unstable_by_key(|&(_, m)| m);
+            expected.sort_unstable_by_key(|&(_, m)| m);
+
+            assert_eq!(outcomes, expected, "{}", board.position());
+        }
+    }
+
+    #[test]
+    fn solvers_give_up_after_deadline() {
+        let board = random_position(&mut Random::new(2), 20, |_| true);
+        let passed = Some(Instant::now());
+
+        assert_eq!(solve_until(&board, board.to_move, passed), None, "{}", board.position());
+        assert_eq!(solve_wld_until(&board, board.to_move, passed), None, "{}", board.position());
+
+        for board in positions() {
+            let later = Some(Instant::now() + std::time::Duration::from_secs(3600));
+
+            assert_eq!(solve_until(&board, board.to_move, later), Some(solve(&board, board.to_move)), "{}", board.position());
+            assert_eq!(solve_wld_until(&board, board.to_move, later), Some(solve_wld(&board, board.to_move)), "{}", board.position());
+        }
+    }
+}
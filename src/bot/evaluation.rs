@@ -1,10 +1,12 @@
 use std::cmp::Ordering;
 
-use crate::game::Board;
+use crate::game::{Board, Bits, DIRECTIONS, shift};
 use super::moves::generate_moves;
+use super::stability::stable_discs;
+use super::probcut::{Cuts, HEURISTIC_CUTS};
 
-// Heatmap of where pieces should roughly be on the board.
-pub const WEIGHT_LOOKUP: [i32; 64] = [ // Very rough should be improved in future iterations
+/// Heatmap of where pieces should roughly be on the board.
+pub const WEIGHT_LOOKUP: [i32; 64] = [ // Set by hand, the tuner only fits the pattern evaluation
     6, 3, 4, 4, 4, 4, 3, 6,
     3, 1, 1, 1, 1, 1, 1, 3,
     4, 1, 2, 2, 2, 2, 1, 4,
@@ -15,57 +17,273 @@ pub const WEIGHT_LOOKUP: [i32; 64] = [ // Very rough should be improved in futur
     6, 3, 4, 4, 4, 4, 3, 6
 ]; 
 
-/// Function to evaluate board states.
+/// Scores board states for the search. The score is from the side of
+/// color, higher is better, and the score for the other side should be
+/// its negative for the search to work well. Needs to be Sync as every
+/// thread of a search shares the same evaluator.
+pub trait Evaluator: Sync {
+    /// # Arguments
+    /// *   board: Board which is evaluated
+    /// *   color: Perspective of evaluation
+    ///
+    /// Returns evaluation of board state.
+    fn evaluate(&self, board: &Board, color: bool) -> i32;
+
+    /// Score of every term of the evaluation, to see why a board scores
+    /// what it does. The terms add up to evaluate.
+    ///
+    /// # Arguments
+    /// *   board: Board which is evaluated
+    /// *   color: Perspective of evaluation
+    ///
+    /// Returns score of every term, None if the game is over or the evaluation has no terms
+    fn breakdown(&self, _board: &Board, _color: bool) -> Option<Breakdown> {
+        None
+    }
+
+    /// Multi-ProbCut parameters fitted for this evaluation, the search
+    /// only cuts with these, as the scores of another evaluation don't
+    /// follow the same fit.
+    ///
+    /// Returns parameters, None if none have been fitted
+    fn cuts(&self) -> Option<&Cuts> {
+        None
+    }
+}
+
+/// Score of every term of an evaluation, in the order they are added up
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Breakdown {
+    /// Name and score of every term
+    pub terms: Vec<(&'static str, i32)>
+}
+
+impl Breakdown {
+    /// Sum of the terms, the evaluation itself
+    pub fn total(&self) -> i32 {
+        self.terms.iter().map(|&(_, score)| score).sum()
+    }
+}
+
+/// Weights of the terms of the hand written evaluation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Weights {
+    /// Ratio of own moves to opponent moves
+    pub mobility: i32,
+    /// Ratio of own pieces to opponent pieces
+    pub pieces: i32,
+    /// Square weights of the pieces, times WEIGHT_LOOKUP
+    pub squares: i32,
+    /// Each stable disc, on top of the weight of its square
+    pub stability: i32,
+    /// Each disc next to an empty tile, taken off as these give the opponent moves
+    pub frontier: i32,
+    /// Each empty tile next to an opponent disc, a move that may open up later
+    pub potential_mobility: i32
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            mobility: 20,
+            pieces: 20,
+            squares: 1,
+            stability: 10,
+            frontier: 10,
+            potential_mobility: 5
+        }
+    }
+}
+
+impl Weights {
+    /// Weights part of the way from self to other
+    ///
+    /// # Arguments
+    /// *   other: Weights that are reached at the end of the way
+    /// *   step: How far along the way, from 0 to steps
+    /// *   steps: Length of the way
+    fn blend(&self, other: &Self, step: u32, steps: u32) -> Self {
+        let (step, steps) = (step as i32, steps as i32);
+        let mix = |a: i32, b: i32| (a * (steps - step) + b * step) / steps;
+
+        Self {
+            mobility: mix(self.mobility, other.mobility),
+            pieces: mix(self.pieces, other.pieces),
+            squares: mix(self.squares, other.squares),
+            stability: mix(self.stability, other.stability),
+            frontier: mix(self.frontier, other.frontier),
+            potential_mobility: mix(self.potential_mobility, other.potential_mobility)
+        }
+    }
+}
+
+/// Number of empty tiles at which each set of weights counts fully
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Phases {
+    /// Midgame weights count fully here, opening weights count fully at the start of the game
+    pub midgame: u32,
+    /// Endgame weights count fully from here to the end of the game
+    pub endgame: u32
+}
+
+impl Default for Phases {
+    fn default() -> Self {
+        Self { midgame: 40, endgame: 20 }
+    }
+}
+
+/// The hand written evaluation, see evaluate. What matters changes a lot
+/// over a game, so there are weights for the opening, the midgame and
+/// the endgame. Between the phases the weights are blended by how many
+/// tiles are empty, so the evaluation never jumps from one move to the next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Heuristic {
+    /// Weights at the start of the game
+    pub opening: Weights,
+    /// Weights in the middle of the game
+    pub midgame: Weights,
+    /// Weights near the end of the game
+    pub endgame: Weights,
+    /// Where each set of weights counts fully
+    pub phases: Phases
+}
+
+impl Heuristic {
+    /// The same weights for the whole game
+    pub fn new(weights: Weights) -> Self {
+        Self { opening: weights, midgame: weights, endgame: weights, phases: Phases::default() }
+    }
+
+    /// Weights used for a board with this many empty tiles
+    pub fn weights(&self, empties: u32) -> Weights {
+        let empties = empties.min(60);
+        let Phases { midgame, endgame } = self.phases;
+
+        if empties > midgame {
+            self.opening.blend(&self.midgame, 60 - empties, 60 - midgame)
+        } else if empties > endgame {
+            self.midgame.blend(&self.endgame, midgame - empties, midgame - endgame)
+        } else {
+            self.endgame
+        }
+    }
+}
+
+impl Default for Heuristic {
+    fn default() -> Self {
+        Self {
+            // Few pieces and good squares early, the pieces themselves late
+            opening: Weights { mobility: 30, pieces: -20, squares: 2, stability: 10, frontier: 10, potential_mobility: 5 },
+            midgame: Weights { mobility: 20, pieces: 0, squares: 1, stability: 10, frontier: 10, potential_mobility: 5 },
+            endgame: Weights { mobility: 10, pieces: 60, squares: 1, stability: 20, frontier: 5, potential_mobility: 2 },
+            phases: Phases::default()
+        }
+    }
+}
+
+impl Evaluator for Heuristic {
+    fn evaluate(&self, board: &Board, color: bool) -> i32 {
+        evaluate_with(board, color, &self.weights(board.empty().count_ones()))
+    }
+
+    fn breakdown(&self, board: &Board, color: bool) -> Option<Breakdown> {
+        breakdown_with(board, color, &self.weights(board.empty().count_ones()))
+    }
+
+    // Fitted for the default weights only, any other weights score differently
+    fn cuts(&self) -> Option<&Cuts> {
+        (*self == Self::default()).then_some(&HEURISTIC_CUTS)
+    }
+}
+
+/// Function to evaluate board states with the default weights of each phase.
 /// # Arguments
 /// *   board: Board which is evaluated
 /// *   color: Perspective of evaluation
+/// 
 /// Returns evaluation of board state.
 pub fn evaluate(board: &Board, color: bool) -> i32 {
-    // TODO: Implement a way to detect volatility of board 
-    // TODO: Implement a way to score pieces connect to side and close to sides
-    // TODO: Optimize constants and factors
-    
-    let mut score = 0; // Score starts at 0
+    Heuristic::default().evaluate(board, color)
+}
+
+/// Function to evaluate board states.
+/// # Arguments
+/// *   board: Board which is evaluated
+/// *   color: Perspective of evaluation
+/// *   weights: How much each term counts
+/// 
+/// Returns evaluation of board state.
+pub fn evaluate_with(board: &Board, color: bool, weights: &Weights) -> i32 {
+    match breakdown_with(board, color, weights) {
+        Some(breakdown) => breakdown.total(),
+        None => match board.piece_count(color).cmp(&board.piece_count(!color)) { // Game Over
+            Ordering::Greater => i32::MAX - 1, // We win
+            _ => i32::MIN + 1 // We lose or tie, (we don't want to tie, only winners here)
+        }
+    }
+}
+
+/// evaluate_with function but the score of every term is kept, not just their sum.
+/// # Arguments
+/// *   board: Board which is evaluated
+/// *   color: Perspective of evaluation
+/// *   weights: How much each term counts
+/// 
+/// Returns score of every term, None if the game is over.
+pub fn breakdown_with(board: &Board, color: bool, weights: &Weights) -> Option<Breakdown> {
+    let mut breakdown = Breakdown::default();
     let moves = generate_moves(board, color); // Gives idea of how many moves are possible
     let opponent_moves = generate_moves(board, !color); // Gives idea of how many moves are possible
 
-    // If no moves are possible a constant value is subtracted, 
-    // depending on the ratio of your moves to opponents moves
-    // A variable amount is added.
+    // Depending on the ratio of your moves to opponents moves
+    // a variable amount is added. If a side has no moves it has
+    // to pass, which is scored as a fixed multiple of the weight.
 
-    if !moves.is_empty() && !opponent_moves.is_empty() {
-        score += ((moves.len() as f32 / opponent_moves.len() as f32) * 20f32) as i32;
+    let mobility = if !moves.is_empty() && !opponent_moves.is_empty() {
+        ((moves.len() as f32 / opponent_moves.len() as f32) * weights.mobility as f32) as i32
 
     } else if moves.is_empty() && opponent_moves.is_empty() {
-        match board.piece_count[&color].cmp(&board.piece_count[&!color]) {
-            Ordering::Greater => return std::i32::MAX - 1, // We win
-            _ => return std::i32::MIN + 1 // We lose or tie, (we don't want to tie, only winners here)
-        }
+        return None // Game Over, there is nothing to estimate
 
-    } else if !moves.is_empty() { // We have no moves
-        score -= 50;
+    } else if moves.is_empty() { // We have no moves
+        -weights.mobility * 5 / 2
 
     } else { // Opponent has no moves
-        score += 40;
-    }
+        weights.mobility * 2
+    };
+    breakdown.terms.push(("Mobility", mobility));
 
     // The more pieces there are on the board, the more it matters 
     // who has more pieces. Early game it is good to have few pieces 
     // to minimize opponents moves and maximize future flips
-    let total_piece_ratio = board.piece_count[&color] as f32 + board.piece_count[&!color] as f32 / 64f32;
-    let piece_ratio = board.piece_count[&color] as f32 / board.piece_count[&!color] as f32;
-    
-    score += ((piece_ratio * 20f32) * total_piece_ratio) as i32; // Adding pieces to the score
+    let total_piece_ratio = (board.piece_count(color) as f32 + board.piece_count(!color) as f32) / 64f32;
+    let piece_ratio = board.piece_count(color) as f32 / board.piece_count(!color) as f32;
     
+    breakdown.terms.push(("Discs", ((piece_ratio * weights.pieces as f32) * total_piece_ratio) as i32));
 
-    board.pieces
-        .into_iter()
-        .enumerate()
-        .map(|(i, piece)| {
-            if let Some(c) = piece { 
-                if c == color {  WEIGHT_LOOKUP[i] } 
-                else { 7-WEIGHT_LOOKUP[i] } 
-            } else { 0 } })
-        .fold(score, |acc, weight| acc + weight)
+    let squares: i32 = Bits(board.bitboard(color))
+        .map(|i| WEIGHT_LOOKUP[i])
+        .chain(Bits(board.bitboard(!color)).map(|i| 7-WEIGHT_LOOKUP[i]))
+        .sum();
+    breakdown.terms.push(("Square weights", squares * weights.squares));
+
+    // Pieces that can never be flipped again are worth more than any square weight says
+    let (player, opponent) = (board.bitboard(color), board.bitboard(!color));
+    let stability = stable_discs(player, opponent).count_ones() as i32 - stable_discs(opponent, player).count_ones() as i32;
+    breakdown.terms.push(("Stability", stability * weights.stability));
+
+    // Pieces next to empty tiles can be flipped soon and hand the opponent moves,
+    // empty tiles next to opponent pieces are where our moves will come from
+    let empty = board.empty();
+    let frontier = |discs: u64| (discs & neighbours(empty)).count_ones() as i32;
+    let potential = |discs: u64| (empty & neighbours(discs)).count_ones() as i32;
+    breakdown.terms.push(("Frontier", (frontier(opponent) - frontier(player)) * weights.frontier));
+    breakdown.terms.push(("Potential mobility", (potential(opponent) - potential(player)) * weights.potential_mobility));
+
+    Some(breakdown)
 }
 
+/// Every tile next to a piece of the bitmap, in any of the 8 directions
+fn neighbours(bitboard: u64) -> u64 {
+    DIRECTIONS.iter().fold(0, |acc, &direction| acc | shift(bitboard, direction))
+}
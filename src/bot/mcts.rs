@@ -0,0 +1,182 @@
+use std::time::{Duration, Instant};
+
+use crate::game::{Board, Move, play_move};
+use super::moves::generate_moves;
+use super::{Random, WEIGHT_LOOKUP};
+
+/// How a game is played out from a new node of the tree
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Playout {
+    /// Every move is picked at random
+    Random,
+    /// Moves on better squares of WEIGHT_LOOKUP are picked more often
+    Heuristic
+}
+
+/// How long the tree is grown for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Budget {
+    /// Fixed number of playouts, the same seed always picks the same move
+    Iterations(u64),
+    /// Milliseconds per move
+    Time(u64)
+}
+
+/// Settings for the Monte-Carlo Tree Search player
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MctsConfig {
+    /// How much moves that have been tried less often are favoured (UCT constant)
+    pub exploration: f32,
+    /// How games are played out
+    pub playout: Playout,
+    /// How long the tree is grown for
+    pub budget: Budget,
+    /// Seed of the random playouts
+    pub seed: u64
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        Self {
+            exploration: std::f32::consts::SQRT_2,
+            playout: Playout::Random,
+            budget: Budget::Time(2000),
+            seed: 0
+        }
+    }
+}
+
+/// One board state in the tree
+struct Node {
+    board: Board,
+    m: Option<Move>, // Move that was played to get here, None for the root
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<Move>, // Moves that don't have a child yet
+    visits: u32,
+    wins: f32 // Wins of the side that moved into this node, a draw is half a win
+}
+
+impl Node {
+    fn new(board: Board, m: Option<Move>, parent: Option<usize>) -> Self {
+        let mut untried: Vec<Move> = generate_moves(&board, board.to_move).into_iter().map(Move::Play).collect();
+        if untried.is_empty() && !generate_moves(&board, !board.to_move).is_empty() {
+            untried.push(Move::Pass);
+        }
+
+        Self { board, m, parent, children: vec![], untried, visits: 0, wins: 0.0 }
+    }
+}
+
+/// Monte-Carlo Tree Search with UCT. Instead of evaluating board states
+/// a tree is grown from the board one node at a time. Each iteration
+/// walks down the tree picking the child with the best balance of win
+/// rate and how rarely it has been tried, adds a child for an untried
+/// move, plays the game out to the end from there and counts the result
+/// for every node on the way back up. The most visited move is played.
+///
+/// # Arguments
+/// *   board: Board the move is picked for
+/// *   color: Side to move
+/// *   config: Exploration constant, playouts and how long to think
+///
+/// Returns most visited move, None if color has no moves
+pub fn mcts(board: &Board, color: bool, config: &MctsConfig) -> Option<usize> {
+    let mut board = *board;
+    board.to_move = color;
+
+    let mut random = Random::new(config.seed);
+    let mut tree = vec![Node::new(board, None, None)];
+
+    let deadline = match config.budget {
+        Budget::Time(time) => Some(Instant::now() + Duration::from_millis(time)),
+        Budget::Iterations(_) => None
+    };
+
+    let mut iterations = 0;
+    loop {
+        match config.budget {
+            Budget::Iterations(budget) if iterations >= budget => break,
+            Budget::Time(_) if iterations > 0 && deadline.is_some_and(|deadline| Instant::now() >= deadline) => break,
+            _ => {}
+        }
+        iterations += 1;
+
+        // Selection, down the tree while every move of a node has a child
+        let mut node = 0;
+        while tree[node].untried.is_empty() && !tree[node].children.is_empty() {
+            node = select(&tree, node, config.exploration);
+        }
+
+        // Expansion, one untried move gets a child
+        if !tree[node].untried.is_empty() {
+            let pick = random.below(tree[node].untried.len());
+            let m = tree[node].untried.swap_remove(pick);
+            let mut child = tree[node].board;
+            play_move(&mut child, m);
+
+            tree.push(Node::new(child, Some(m), Some(node)));
+            let child = tree.len() - 1;
+            tree[node].children.push(child);
+            node = child;
+        }
+
+        // Simulation and backpropagation
+        let winner = playout(tree[node].board, config.playout, &mut random);
+        let mut current = Some(node);
+        while let Some(index) = current {
+            let mover = tree[index].parent.map(|parent| tree[parent].board.to_move);
+
+            tree[index].visits += 1;
+            tree[index].wins += match (winner, mover) {
+                (Some(winner), Some(mover)) if winner == mover => 1.0,
+                (None, _) => 0.5,
+                _ => 0.0
+            };
+            current = tree[index].parent;
+        }
+    }
+
+    let best = tree[0].children.iter().max_by_key(|&&child| tree[child].visits)?;
+    match tree[*best].m {
+        Some(Move::Play(m)) => Some(m),
+        _ => None // Color had to pass
+    }
+}
+
+/// Picks the child with the highest upper confidence bound (UCB1)
+fn select(tree: &[Node], node: usize, exploration: f32) -> usize {
+    let parent_visits = (tree[node].visits as f32).ln();
+
+    let ucb = |child: usize| {
+        let child = &tree[child];
+        let visits = child.visits as f32;
+        child.wins / visits + exploration * (parent_visits / visits).sqrt()
+    };
+
+    *tree[node].children
+        .iter()
+        .max_by(|&&a, &&b| ucb(a).total_cmp(&ucb(b)))
+        .unwrap() // Only called on nodes with children
+}
+
+/// Plays a game out to the end
+///
+/// Returns color of the winner, None for a draw
+fn playout(mut board: Board, playout: Playout, random: &mut Random) -> Option<bool> {
+    loop {
+        let m = match playout {
+            Playout::Random => random.pick_move(&board),
+            Playout::Heuristic => random.weighted_move(&board, |m| WEIGHT_LOOKUP[m] as usize) // Likely as the weight of its square
+        };
+
+        let Some(m) = m else { break }; // Game Over
+        play_move(&mut board, m);
+    }
+
+    match board.piece_count(true).cmp(&board.piece_count(false)) {
+        std::cmp::Ordering::Greater => Some(true),
+        std::cmp::Ordering::Less => Some(false),
+        std::cmp::Ordering::Equal => None
+    }
+}
@@ -1,45 +1,178 @@
+//! The engine, everything the bot needs to pick a move
+
 pub mod moves;
+pub mod probcut;
+pub mod pattern;
 mod search;
 mod evaluation;
+mod transposition;
+mod endgame;
+mod random;
+mod mcts;
+mod stability;
+
+pub use search::{search, search_moves, iterative_search, analyze, Analysis};
+pub use endgame::{solve, solve_until, solve_moves, solve_wld, solve_wld_until, Outcome, DEFAULT_ENDGAME_EMPTIES, DEFAULT_WLD_EMPTIES};
+pub use evaluation::{WEIGHT_LOOKUP, evaluate, evaluate_with, breakdown_with, Evaluator, Breakdown, Heuristic, Weights, Phases};
+pub use random::Random;
+pub use mcts::{mcts, MctsConfig, Playout, Budget};
+pub use pattern::PatternEvaluator;
+pub use stability::stable_discs;
+pub use transposition::{TranspositionTable, DEFAULT_ENTRIES};
 
-pub use search::search;
-pub use evaluation::{WEIGHT_LOOKUP, evaluate};
+use std::time::{Duration, Instant};
 
-use crate::game::Board;
-use crate::human::to_notation;
+use crate::game::{Board, Move};
 use moves::generate_moves;
 use crate::game;
 
+/// How long the bot is allowed to think for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    /// Fixed depth that is searched
+    Depth(u32),
+    /// Milliseconds per move, searched with iterative deepening
+    Time(u64)
+}
+
+/// How the bot picks its moves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Engine {
+    /// Alpha-beta search of the evaluation, with the endgame solvers
+    AlphaBeta,
+    /// Monte-Carlo Tree Search of playouts, for the whole game
+    Mcts
+}
+
+/// Settings for how the bot plays
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    /// Which engine picks the moves
+    pub engine: Engine,
+    /// How long the midgame search may think for
+    pub limit: Limit,
+    /// Game is solved exactly once this many tiles or fewer are empty
+    pub endgame_empties: u32,
+    /// Game is solved for win, loss or draw once this many tiles or fewer are empty
+    pub wld_empties: u32,
+    /// Whether the midgame search skips deep searches that shallow searches predict (Multi-ProbCut).
+    /// Only evaluators with fitted parameters are cut (Evaluator::cuts), with others it does nothing.
+    /// Off by default, in self-play it is about as strong as the full search at the same time per move
+    pub probcut: bool,
+    /// Number of threads the midgame search runs on, 1 keeps it deterministic
+    pub threads: usize,
+    /// Settings for the Monte-Carlo Tree Search engine
+    pub mcts: MctsConfig
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            engine: Engine::AlphaBeta,
+            limit: Limit::Time(2000),
+            endgame_empties: DEFAULT_ENDGAME_EMPTIES,
+            wld_empties: DEFAULT_WLD_EMPTIES,
+            probcut: false,
+            threads: 1,
+            mcts: MctsConfig::default()
+        }
+    }
+}
+
 /// Bot play function. Combines search and playing out move on board for bot.
 /// 
 /// # Arguments
 /// *   board: State of board
 /// *   color: Color of bot
-/// *   max_depth: Depth that is searched (Will switch to Iterative Deepening in the future)
-pub fn play(board: &mut Board, color: bool, max_depth: u32 ) {
+/// *   config: Settings for how the move is searched
+/// *   tt: Transposition table of the normal search, kept from move to move
+/// 
+/// Returns move that was played, None if the game is over
+pub fn play(board: &mut Board, color: bool, config: &Config, tt: &TranspositionTable) -> Option<Move> {
+    let m = choose_move(board, color, config, tt)?;
+    game::play_move(board, m);
+    Some(m)
+}
+
+/// Picks the move the bot would play without playing it, with the hand
+/// written evaluation. See choose_move_with for other evaluators.
+/// 
+/// # Arguments
+/// *   board: State of board
+/// *   color: Color of bot
+/// *   config: Settings for how the move is searched
+/// *   tt: Transposition table of the normal search, kept from move to move
+/// 
+/// Returns move for color, None if the game is over
+pub fn choose_move(board: &Board, color: bool, config: &Config, tt: &TranspositionTable) -> Option<Move> {
+    choose_move_with(board, color, config, &Heuristic::default(), tt)
+}
+
+/// Picks the move the bot would play without playing it. Depending on
+/// how many tiles are empty this is the exact solver, the win/loss/draw
+/// solver or the normal search, unless the Monte-Carlo engine is picked.
+/// With a time limit the solvers may use half of it, if they run out the
+/// normal search picks the move with the time that is left. A lost
+/// position is also left to the normal search, with the time that is left.
+/// 
+/// # Arguments
+/// *   board: State of board
+/// *   color: Color of bot
+/// *   config: Settings for how the move is searched
+/// *   evaluator: Scores board states for the normal search
+/// *   tt: Transposition table of the normal search, kept from move to move
+/// 
+/// Returns move for color, None if the game is over
+pub fn choose_move_with<E: Evaluator + ?Sized>(board: &Board, color: bool, config: &Config, evaluator: &E, tt: &TranspositionTable) -> Option<Move> {
     if generate_moves(board, color).is_empty() {
         if generate_moves(board, !color).is_empty() {
-            match board.piece_count[&color].cmp(&board.piece_count[&!color]) {
-                std::cmp::Ordering::Greater => println!("You Won! Piece Ratio = {}:{} (You:Bot)", board.piece_count[&color], board.piece_count[&!color]),
-                std::cmp::Ordering::Equal => println!("A It's a tie! Piece Ratio = {}:{} (You:Bot)", board.piece_count[&color], board.piece_count[&!color]),
-                std::cmp::Ordering::Less => println!("You Lost! Piece Ratio = {}:{} (You:Bot)", board.piece_count[&color], board.piece_count[&!color]),
-            }
-            std::process::exit(0);
-        } else {
-            return
+            return None // Game Over
         }
+
+        return Some(Move::Pass)
     }
 
-    let mut moves = search::search(board, max_depth, color);
-    moves.sort_by_key(|(score, _)| *score);
-    let m = moves.last().unwrap().1;
+    if config.engine == Engine::Mcts {
+        return mcts(board, color, &config.mcts).map(Move::Play)
+    }
 
-    // let mut m = match search::iterative_search(board, color, 2000) {
-    //     Some(m) => m,
-    //     None => panic!("Time was not sufficient to find moves increase thinking time")
-    // };
+    // With a time limit the solvers get half of it, the normal search takes over
+    // with what is left if they don't finish. With a depth limit they always finish
+    let start = Instant::now();
+    let deadline = match config.limit {
+        Limit::Time(time) => Some(start + Duration::from_millis(time / 2)),
+        Limit::Depth(_) => None
+    };
 
-    game::play(board, m, color);
-    println!("{}{}", to_notation(m).0, to_notation(m).1);
-    // println!("{:?}", flips[1..].iter().map(|&i| to_notation(i)));
-}
\ No newline at end of file
+    let empties = board.empty().count_ones();
+    if empties <= config.endgame_empties {
+        if let Some((_, Some(m))) = solve_until(board, color, deadline) {
+            return Some(Move::Play(m))
+        }
+    } else if empties <= config.wld_empties {
+        // Win or draw is taken straight away, a lost position is left to
+        // the normal search which at least tries to make losing hard
+        let best = solve_wld_until(board, color, deadline)
+            .and_then(|wld| wld.into_iter().rev().max_by_key(|(outcome, _)| *outcome)) // rev keeps the first of equal outcomes
+            .filter(|&(outcome, _)| outcome != Outcome::Loss);
+
+        if let Some((_, m)) = best {
+            return Some(Move::Play(m))
+        }
+    }
+
+    let config = match config.limit {
+        Limit::Time(time) => Config { limit: Limit::Time(time.saturating_sub(start.elapsed().as_millis() as u64)), ..*config },
+        Limit::Depth(_) => *config
+    };
+
+    Some(Move::Play(search_move(board, color, &config, evaluator, tt)))
+}
+
+/// Picks a move with the normal search, board must have moves for color
+fn search_move<E: Evaluator + ?Sized>(board: &Board, color: bool, config: &Config, evaluator: &E, tt: &TranspositionTable) -> usize {
+    match analyze(board, color, config, evaluator, tt).unwrap().pv[0] {
+        Move::Play(m) => m,
+        Move::Pass => unreachable!("root moves are never passes")
+    }
+}
@@ -1,4 +1,39 @@
-use crate::game::{Board, validate, play, unplay};
+use crate::game::{Board, Bits, DIRECTIONS, shift, play, unplay};
+
+/// Finds every valid move at once. From each of the side's pieces a run
+/// of opponent pieces is grown in every direction with shifts, any empty
+/// tile directly past the end of a run is a valid move.
+///
+/// # Arguments
+/// *   board: Board state where moves can be played
+/// *   color: Side which moves are valid for
+///
+/// Returns bitmap of moves
+pub fn move_mask(board: &Board, color: bool) -> u64 {
+    bitboard_moves(board.bitboard(color), board.bitboard(!color))
+}
+
+/// move_mask function but bitmaps of both sides are inputted, for code
+/// that keeps its own bitmaps instead of a Board
+///
+/// # Arguments
+/// *   player: Bitmap of side whose moves are found
+/// *   opponent: Bitmap of other side
+pub fn bitboard_moves(player: u64, opponent: u64) -> u64 {
+    let empty = !(player | opponent);
+
+    let mut moves = 0;
+    for direction in DIRECTIONS {
+        let mut line = shift(player, direction) & opponent;
+        for _ in 0..5 { // A run of opponent pieces is at most 6 long
+            line |= shift(line, direction) & opponent;
+        }
+
+        moves |= shift(line, direction) & empty;
+    }
+
+    moves
+}
 
 /// Simple move generation function
 /// 
@@ -8,47 +43,7 @@ use crate::game::{Board, validate, play, unplay};
 /// 
 /// Returns vector of moves
 pub fn generate_moves(board: &Board, color: bool) -> Vec<usize> {
-    // TODO: Only check positions adjacent to pieces in future
-    // TODO: Make pruned positions Cached
-    // pruned_positions(board)
-    (0..64)
-        .into_iter()
-        .filter(|&i| validate(board, i, color))
-        .collect()
-}
-
-/// Function to prune positions that are checked
-/// Too slow to work
-#[allow(dead_code)]
-fn pruned_positions(board: &Board) -> Vec<usize>  {
-    let offsets: [i32; 8] = [-9, -8, -7, -1, 1, 7, 8, 9];
-    (0..64)
-        .filter(|&i| {
-            let mut neighbor_found = false;
-            for offset in offsets {
-                if i + offset > 0 && i + offset < 63 && board.pieces[(i + offset) as usize].is_some() {
-                    neighbor_found = true;
-                    break
-                } 
-            }
-            neighbor_found
-        })
-        .map(|i| i as usize)
-        .collect()
-    
-    
-    // let mut tiles = vec![];
-    // for i in 0..64 {
-    //     let neighbors = [-9, -8, -7, -1, 1, 7, 8, 9];
-    //     for offset in neighbors {
-    //         if i + offset > 0 && i + offset < 63 && board.pieces[(i + offset) as usize].is_some() {
-    //             tiles.push(i);
-    //             break
-    //         } 
-    //     }
-    // }
-
-
+    Bits(move_mask(board, color)).collect()
 }
 
 /// Simple test for seeing how many moves are evaluated at base of game tree
@@ -77,7 +72,7 @@ pub fn _ply_move_counter_test(board: &Board, depth: u32, color: bool) -> usize {
     if moves.is_empty() { return 0 }
     else if depth == 0 { return moves.len() }
 
-    let mut board = board.clone();
+    let mut board = *board;
     moves
         .into_iter()
         .map(|m| {
@@ -87,4 +82,19 @@ pub fn _ply_move_counter_test(board: &Board, depth: u32, color: bool) -> usize {
             count })
         .reduce(|acc, c| acc + c)
         .unwrap()
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Counts documented on _ply_move_counter_test, any wrong shift or flip changes them
+    #[test]
+    fn ply_move_counts() {
+        let counts = [12, 56, 244, 1396, 8200, 55092, 390216, 3005264];
+
+        for (depth, &count) in (1..).zip(&counts) {
+            assert_eq!(_ply_move_counter_test(&Board::new(), depth, true), count, "ply {depth}");
+        }
+    }
+}
@@ -1,5 +1,7 @@
+//! Finding and ordering moves
+
 mod generation;
 mod ordering;
 
-pub use generation::{generate_moves, _ply_move_counter_test};
-pub use ordering::heuristic_order;
\ No newline at end of file
+pub use generation::{generate_moves, move_mask, bitboard_moves, _ply_move_counter_test};
+pub use ordering::{heuristic_order, replies, MoveHistory};
@@ -1,13 +1,116 @@
-use crate::bot::evaluation::WEIGHT_LOOKUP;
+use std::cmp::Reverse;
+
+use crate::bot::WEIGHT_LOOKUP;
+use crate::game::{Board, bitboard_flips};
+use super::bitboard_moves;
+
+// Number of killer moves kept for each ply
+const KILLERS_PER_PLY: usize = 2;
+
+// What each part of the ordering score of a move is worth: every point of square weight,
+// the best history among the moves, and every reply left to the opponent
+const SQUARE_SCORE: i32 = 16;
+const HISTORY_SCORE: i32 = 32;
+const REPLY_SCORE: i32 = 16;
 
 /// Very very simple, lightweight move sorting function.
 /// Only factor currently considered is position on board,
-/// in future this function will be made to resemble to state
-/// evaluation function but further optimizations will be 
-/// necessary before then
-/// 
+/// best squares go first. Used where the search has not
+/// learned anything yet, see MoveHistory for the rest
+///
 /// # Arguments
 /// *   moves: Moves to be sorted
 pub fn heuristic_order(moves: &mut [usize]) {
-    moves.sort_by_key(|&i| WEIGHT_LOOKUP[i])
-}
\ No newline at end of file
+    moves.sort_by_key(|&i| Reverse(WEIGHT_LOOKUP[i]))
+}
+
+/// Counts the moves the opponent has after a move, on bitmaps of both sides
+///
+/// # Arguments
+/// *   player: Bitmap of side making the move
+/// *   opponent: Bitmap of other side
+/// *   i: Index of move on main board
+pub fn replies(player: u64, opponent: u64, i: usize) -> u32 {
+    let flips = bitboard_flips(player, opponent, i);
+    bitboard_moves(opponent & !flips, player | flips | 1 << i).count_ones()
+}
+
+/// What the search has learned about which moves cause cutoffs.
+/// Killer moves are the last moves that caused a cutoff at a ply,
+/// a move that refutes one line often refutes its siblings too.
+/// The history table adds up how often and how deep each move
+/// caused a cutoff for each side, anywhere in the tree.
+pub struct MoveHistory {
+    killers: Vec<[Option<usize>; KILLERS_PER_PLY]>, // Most recent killer first
+    history: [[u32; 64]; 2]
+}
+
+impl MoveHistory {
+    /// Creates an empty history, ordering is by square weights only
+    pub fn new() -> Self {
+        Self { killers: vec![], history: [[0; 64]; 2] }
+    }
+
+    /// Records a move that caused a beta cutoff
+    ///
+    /// # Arguments
+    /// *   m: Move that caused the cutoff
+    /// *   ply: Number of moves played since the root
+    /// *   color: Side that played the move
+    /// *   depth: Depth left to search when it caused the cutoff
+    pub fn cutoff(&mut self, m: usize, ply: usize, color: bool, depth: u32) {
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None; KILLERS_PER_PLY]);
+        }
+
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(m) {
+            killers.rotate_right(1);
+            killers[0] = Some(m);
+        }
+
+        // Deep cutoffs save the most work, so they count the most
+        let history = &mut self.history[color as usize][m];
+        *history = history.saturating_add(depth * depth);
+    }
+
+    /// Halves the history table, so that what was learned in earlier
+    /// searches slowly gives way to what is learned in the next one
+    pub fn age(&mut self) {
+        self.history.iter_mut().flatten().for_each(|history| *history /= 2);
+    }
+
+    /// Sorts moves so the ones most likely to cause a cutoff go first.
+    /// Killer moves of the ply go first, the rest are sorted by one score:
+    /// the weight of the square, plus the history of the move scaled
+    /// against the best history of the side, less the replies the move
+    /// leaves the opponent when those are counted (fastest-first, see replies)
+    ///
+    /// # Arguments
+    /// *   board: Board state the moves are played on
+    /// *   moves: Moves to be sorted
+    /// *   ply: Number of moves played since the root
+    /// *   color: Side whose moves are being sorted
+    /// *   mobility: Whether the replies of every move are counted
+    pub fn order(&self, board: &Board, moves: &mut [usize], ply: usize, color: bool, mobility: bool) {
+        let killers = self.killers.get(ply).copied().unwrap_or([None; KILLERS_PER_PLY]);
+        let history = &self.history[color as usize];
+        let best_history = moves.iter().map(|&i| history[i]).max().unwrap_or(0).max(1) as u64;
+        let (player, opponent) = (board.bitboard(color), board.bitboard(!color));
+
+        moves.sort_by_cached_key(|&i| {
+            let killer = killers.iter().position(|&killer| killer == Some(i)).unwrap_or(KILLERS_PER_PLY);
+            let mut score = WEIGHT_LOOKUP[i] * SQUARE_SCORE + (history[i] as u64 * HISTORY_SCORE as u64 / best_history) as i32;
+            if mobility {
+                score -= replies(player, opponent, i) as i32 * REPLY_SCORE;
+            }
+            (killer, Reverse(score))
+        });
+    }
+}
+
+impl Default for MoveHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
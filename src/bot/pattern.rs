@@ -0,0 +1,267 @@
+//! Pattern evaluation, scoring a board by looking up groups of tiles
+//!
+//! A pattern is a fixed group of tiles, like an edge with its two X
+//! squares or the 3x3 block in a corner. Every way the tiles of a pattern
+//! can be filled (empty, own piece or opponent piece) is a base 3 number,
+//! which indexes a table of weights. Each pattern is laid on the board in
+//! every rotation and reflection that gives a new group of tiles, all of
+//! them sharing one table, and the score of a board is the sum of the
+//! weights of every pattern. A separate set of tables is kept for every
+//! stage of the game, as what a shape is worth changes as the board fills.
+//!
+//! The weights are in hundredths of a disc of the final score. They are
+//! read from a file written by the tuner, without one the tables are
+//! seeded from WEIGHT_LOOKUP so the evaluator still plays sensibly.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::game::Board;
+use super::moves::move_mask;
+use super::evaluation::{Breakdown, Evaluator, WEIGHT_LOOKUP};
+
+/// Number of game stages with their own weights
+pub const STAGES: usize = 13;
+
+/// Evaluation units per disc of the final score
+pub const DISC_SCALE: i32 = 100;
+
+// Start of a weights file, followed by the version, stages and features
+const MAGIC: &[u8; 4] = b"OTPW";
+const VERSION: u32 = 1;
+
+/// Name and tiles of each pattern in one orientation, the README has pictures
+const PATTERNS: [(&str, &[usize]); 11] = [
+    ("Edge and both X squares", &[0, 1, 2, 3, 4, 5, 6, 7, 9, 14]),
+    ("3x3 corner", &[0, 1, 2, 8, 9, 10, 16, 17, 18]),
+    ("2x5 corner", &[0, 1, 2, 3, 4, 8, 9, 10, 11, 12]),
+    ("Main diagonal", &[0, 9, 18, 27, 36, 45, 54, 63]),
+    ("Diagonal of 7", &[1, 10, 19, 28, 37, 46, 55]),
+    ("Diagonal of 6", &[2, 11, 20, 29, 38, 47]),
+    ("Diagonal of 5", &[3, 12, 21, 30, 39]),
+    ("Diagonal of 4", &[4, 13, 22, 31]),
+    ("Second row", &[8, 9, 10, 11, 12, 13, 14, 15]),
+    ("Third row", &[16, 17, 18, 19, 20, 21, 22, 23]),
+    ("Fourth row", &[24, 25, 26, 27, 28, 29, 30, 31])
+];
+
+/// Number of weights of one stage, every filling of every pattern
+pub const FEATURES: usize = {
+    let mut features = 0;
+    let mut i = 0;
+    while i < PATTERNS.len() {
+        features += 3usize.pow(PATTERNS[i].1.len() as u32);
+        i += 1;
+    }
+    features
+};
+
+/// One pattern laid on the board
+struct Instance {
+    pattern: usize, // Index into PATTERNS
+    tiles: Vec<usize>,
+    offset: usize // Where the weights of the pattern start
+}
+
+impl Instance {
+    /// Base 3 number of how the tiles are filled, the first tile is the lowest digit
+    fn index(&self, player: u64, opponent: u64) -> usize {
+        self.tiles.iter().rev().fold(0, |index, &i| {
+            index * 3 + (player >> i & 1) as usize + 2 * (opponent >> i & 1) as usize
+        })
+    }
+}
+
+/// Every pattern in every orientation, built once
+fn instances() -> &'static [Instance] {
+    static INSTANCES: OnceLock<Vec<Instance>> = OnceLock::new();
+
+    INSTANCES.get_or_init(|| {
+        let mut instances: Vec<Instance> = vec![];
+        let mut offset = 0;
+
+        for (pattern, (_, tiles)) in PATTERNS.iter().enumerate() {
+            let mut seen: Vec<Vec<usize>> = vec![];
+
+            for symmetry in 0..8 {
+                let tiles: Vec<usize> = tiles.iter().map(|&i| transform(i, symmetry)).collect();
+
+                // A symmetry can give the same tiles in another order, which would count them twice
+                let mut set = tiles.clone();
+                set.sort_unstable();
+                if !seen.contains(&set) {
+                    seen.push(set);
+                    instances.push(Instance { pattern, tiles, offset });
+                }
+            }
+
+            offset += 3usize.pow(tiles.len() as u32);
+        }
+
+        instances
+    })
+}
+
+/// Tile i after one of the 8 rotations and reflections of the board
+fn transform(i: usize, symmetry: usize) -> usize {
+    let (mut row, mut column) = (i / 8, i % 8);
+
+    if symmetry & 1 != 0 {
+        column = 7 - column;
+    }
+    if symmetry & 2 != 0 {
+        row = 7 - row;
+    }
+    if symmetry & 4 != 0 {
+        (row, column) = (column, row);
+    }
+
+    row * 8 + column
+}
+
+/// Stage of the game from the number of empty tiles, 0 at the start
+pub fn stage(empties: u32) -> usize {
+    (60 - empties.min(60)) as usize * STAGES / 61
+}
+
+/// Which weight of a stage every pattern of the board looks up, from the side of color
+pub fn features(board: &Board, color: bool) -> impl Iterator<Item = usize> + '_ {
+    let (player, opponent) = (board.bitboard(color), board.bitboard(!color));
+    instances().iter().map(move |instance| instance.offset + instance.index(player, opponent))
+}
+
+/// Evaluator that adds up the weights of every pattern on the board
+#[derive(Clone, Debug)]
+pub struct PatternEvaluator {
+    weights: Vec<i16> // STAGES tables of FEATURES weights
+}
+
+impl PatternEvaluator {
+    /// Weights seeded from WEIGHT_LOOKUP, a piece is worth its square
+    /// weight split evenly between the patterns covering the square
+    pub fn new() -> Self {
+        let mut coverage = [0; 64];
+        for instance in instances() {
+            for &i in &instance.tiles {
+                coverage[i] += 1;
+            }
+        }
+
+        // Every orientation of a pattern shares its weights, and the square weights are
+        // symmetric, so the tiles of the first orientation seed them
+        let mut stage = vec![0i16; FEATURES];
+        let mut offset = 0;
+        for (_, pattern) in PATTERNS {
+            let size = 3usize.pow(pattern.len() as u32);
+
+            for (index, weight) in stage[offset..offset + size].iter_mut().enumerate() {
+                let mut digits = index;
+                let mut score = 0.0;
+
+                for &i in pattern {
+                    // A square weight of 2 is worth a disc
+                    let value = WEIGHT_LOOKUP[i] as f32 * DISC_SCALE as f32 / 2.0 / coverage[i] as f32;
+                    match digits % 3 {
+                        1 => score += value,
+                        2 => score -= value,
+                        _ => {}
+                    }
+                    digits /= 3;
+                }
+
+                *weight = score.round() as i16;
+            }
+
+            offset += size;
+        }
+
+        Self { weights: stage.repeat(STAGES) }
+    }
+
+    /// # Arguments
+    /// *   weights: STAGES tables of FEATURES weights, one stage after the other
+    ///
+    /// Returns evaluator, None if there are not exactly enough weights
+    pub fn from_weights(weights: Vec<i16>) -> Option<Self> {
+        (weights.len() == STAGES * FEATURES).then_some(Self { weights })
+    }
+
+    /// All weights, one stage after the other
+    pub fn weights(&self) -> &[i16] {
+        &self.weights
+    }
+
+    /// Reads weights written by save
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        let mut file = io::BufReader::new(fs::File::open(path)?);
+        let mut header = [0; 16];
+        file.read_exact(&mut header)?;
+
+        let word = |n: usize| u32::from_le_bytes(header[n * 4..n * 4 + 4].try_into().unwrap());
+        if &header[..4] != MAGIC {
+            return Err(invalid("not a pattern weights file"))
+        }
+        if word(1) != VERSION || word(2) as usize != STAGES || word(3) as usize != FEATURES {
+            return Err(invalid("weights are for a different version of the patterns"))
+        }
+
+        let mut bytes = vec![0; STAGES * FEATURES * 2];
+        file.read_exact(&mut bytes)?;
+
+        let weights = bytes.chunks_exact(2).map(|pair| i16::from_le_bytes([pair[0], pair[1]])).collect();
+        Ok(Self { weights })
+    }
+
+    /// Writes the weights as a small header followed by every weight as
+    /// a little endian i16
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+
+        file.write_all(MAGIC)?;
+        for word in [VERSION, STAGES as u32, FEATURES as u32] {
+            file.write_all(&word.to_le_bytes())?;
+        }
+        for weight in &self.weights {
+            file.write_all(&weight.to_le_bytes())?;
+        }
+
+        file.flush()
+    }
+}
+
+impl Default for PatternEvaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Evaluator for PatternEvaluator {
+    fn evaluate(&self, board: &Board, color: bool) -> i32 {
+        if move_mask(board, color) | move_mask(board, !color) == 0 {
+            // Game over, the final score is known
+            return (board.piece_count(color) as i32 - board.piece_count(!color) as i32) * DISC_SCALE
+        }
+
+        let stage = &self.weights[stage(board.empty().count_ones()) * FEATURES..][..FEATURES];
+        features(board, color).map(|feature| stage[feature] as i32).sum()
+    }
+
+    fn breakdown(&self, board: &Board, color: bool) -> Option<Breakdown> {
+        if move_mask(board, color) | move_mask(board, !color) == 0 {
+            return None // Game Over
+        }
+
+        // One term for each pattern, with the weights of all of its orientations added up
+        let mut terms: Vec<(&'static str, i32)> = PATTERNS.iter().map(|&(name, _)| (name, 0)).collect();
+        let stage = &self.weights[stage(board.empty().count_ones()) * FEATURES..][..FEATURES];
+        for (instance, feature) in instances().iter().zip(features(board, color)) {
+            terms[instance.pattern].1 += stage[feature] as i32;
+        }
+
+        Some(Breakdown { terms })
+    }
+}
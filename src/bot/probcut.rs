@@ -0,0 +1,145 @@
+//! Multi-ProbCut, cutting off deep searches a shallow search already predicts
+//!
+//! The score of a deep search is close to a linear function of the score
+//! of a shallow search of the same board, `deep = a * shallow + b`, with
+//! an error that is roughly normal with deviation `sigma`. If the shallow
+//! score says the deep score is very likely above beta (or below alpha)
+//! the deep search is skipped. Every deep depth has several checks, a
+//! shallow depth and a threshold each, tried from the cheapest up.
+//!
+//! The parameters only hold for the evaluation they were fitted with, so
+//! each evaluator gives its own table (Evaluator::cuts), fitted for every
+//! depth, check and game phase with `cargo run --release --bin fit_probcut`.
+
+/// Number of game phases with their own parameters
+pub const PHASES: usize = 6;
+
+/// Smallest depth that is cut, below it the shallow search saves nothing
+pub const MIN_DEPTH: u32 = 3;
+
+/// Deepest depth with fitted parameters, deeper searches use these too
+pub const MAX_DEPTH: u32 = 10;
+
+/// Number of shallow searches that can predict one deep search
+pub const CHECKS: usize = 2;
+
+/// How many deviations the predicted score of each check has to be past
+/// the window to cut, higher is safer but cuts less. The shallowest check
+/// is the cheapest and the least accurate, so it has to be the surest
+pub const THRESHOLDS: [f32; CHECKS] = [2.0, 1.5];
+
+/// Fitted relation between the shallow and the deep score of one depth, check and phase
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cut {
+    /// Slope, how much the deep score moves with the shallow score
+    pub a: f32,
+    /// Offset of the deep score
+    pub b: f32,
+    /// Deviation of the deep score from the prediction
+    pub sigma: f32
+}
+
+impl Cut {
+    /// Creates parameters, for writing out the table
+    pub const fn new(a: f32, b: f32, sigma: f32) -> Self {
+        Self { a, b, sigma }
+    }
+}
+
+/// Parameters of an evaluator for every depth from 0 to MAX_DEPTH, every
+/// check and every phase. Depths below MIN_DEPTH, checks a depth doesn't
+/// have and fits without enough samples have a slope of 0 and never cut
+pub type Cuts = [[[Cut; PHASES]; CHECKS]; MAX_DEPTH as usize + 1];
+
+/// Parameters for the default Heuristic, fitted from 20 games
+pub const HEURISTIC_CUTS: Cuts = [
+    [ // Depth 0
+        [Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0)],
+        [Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0)]
+    ],
+    [ // Depth 1
+        [Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0)],
+        [Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0)]
+    ],
+    [ // Depth 2
+        [Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0)],
+        [Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0)]
+    ],
+    [ // Depth 3
+        [Cut::new(0.837, -19.1, 14.5), Cut::new(0.937, -14.4, 14.2), Cut::new(1.039, -4.8, 26.0), Cut::new(1.166, 17.5, 31.9), Cut::new(1.145, 19.0, 58.5), Cut::new(1.112, 17.5, 76.0)],
+        [Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0)]
+    ],
+    [ // Depth 4
+        [Cut::new(0.926, 11.6, 13.5), Cut::new(0.936, 12.3, 14.3), Cut::new(1.001, 7.0, 25.9), Cut::new(1.128, -13.4, 24.4), Cut::new(1.140, -26.5, 53.9), Cut::new(1.153, -46.1, 79.0)],
+        [Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0), Cut::new(0.0, 0.0, 0.0)]
+    ],
+    [ // Depth 5
+        [Cut::new(0.723, -33.5, 22.9), Cut::new(0.870, -29.2, 18.7), Cut::new(1.028, -15.4, 32.8), Cut::new(1.299, 32.0, 42.8), Cut::new(1.292, 44.2, 90.7), Cut::new(1.299, 58.1, 125.5)],
+        [Cut::new(0.942, -10.0, 13.6), Cut::new(0.931, -15.6, 12.2), Cut::new(0.994, -9.9, 18.5), Cut::new(1.116, 12.9, 19.9), Cut::new(1.128, 22.2, 52.5), Cut::new(1.163, 36.6, 87.7)]
+    ],
+    [ // Depth 6
+        [Cut::new(0.832, 25.9, 20.7), Cut::new(0.885, 24.0, 17.6), Cut::new(1.033, 9.7, 33.1), Cut::new(1.260, -32.8, 35.4), Cut::new(1.298, -62.6, 87.3), Cut::new(1.313, -86.3, 136.6)],
+        [Cut::new(0.949, 10.4, 11.9), Cut::new(0.945, 12.3, 10.9), Cut::new(1.033, 2.3, 19.2), Cut::new(1.119, -18.2, 19.5), Cut::new(1.154, -35.6, 53.3), Cut::new(1.146, -41.2, 92.3)]
+    ],
+    [ // Depth 7
+        [Cut::new(0.623, -47.4, 28.3), Cut::new(0.816, -42.1, 21.2), Cut::new(1.091, -16.7, 45.8), Cut::new(1.447, 49.7, 56.7), Cut::new(1.459, 71.9, 126.8), Cut::new(1.434, 59.3, 153.9)],
+        [Cut::new(0.875, -21.5, 20.1), Cut::new(0.877, -29.0, 15.7), Cut::new(1.070, -8.6, 32.3), Cut::new(1.247, 29.1, 34.0), Cut::new(1.283, 49.1, 84.6), Cut::new(1.281, 32.5, 138.0)]
+    ],
+    [ // Depth 8
+        [Cut::new(0.738, 42.8, 24.8), Cut::new(0.826, 37.0, 19.9), Cut::new(1.097, 9.8, 46.3), Cut::new(1.399, -52.0, 47.7), Cut::new(1.447, -95.1, 118.5), Cut::new(0.0, 0.0, 0.0)],
+        [Cut::new(0.883, 24.9, 16.7), Cut::new(0.885, 25.7, 14.5), Cut::new(1.114, -0.8, 31.9), Cut::new(1.244, -36.1, 32.8), Cut::new(1.296, -67.6, 82.3), Cut::new(0.0, 0.0, 0.0)]
+    ],
+    [ // Depth 9
+        [Cut::new(0.826, -32.2, 23.5), Cut::new(0.823, -42.7, 18.7), Cut::new(1.167, -4.4, 45.0), Cut::new(1.372, 44.5, 49.3), Cut::new(1.418, 72.0, 116.2), Cut::new(0.0, 0.0, 0.0)],
+        [Cut::new(0.958, -15.8, 14.5), Cut::new(0.888, -28.5, 14.3), Cut::new(1.212, 13.2, 29.6), Cut::new(1.234, 29.4, 37.7), Cut::new(1.264, 45.2, 82.2), Cut::new(0.0, 0.0, 0.0)]
+    ],
+    [ // Depth 10
+        [Cut::new(0.690, 52.5, 26.9), Cut::new(0.765, 50.9, 22.7), Cut::new(1.190, 4.7, 61.4), Cut::new(1.552, -77.9, 64.2), Cut::new(1.552, -118.1, 151.0), Cut::new(0.0, 0.0, 0.0)],
+        [Cut::new(0.936, 19.7, 12.0), Cut::new(0.877, 28.4, 13.7), Cut::new(1.220, -18.4, 29.2), Cut::new(1.239, -39.2, 37.8), Cut::new(1.254, -56.8, 85.6), Cut::new(0.0, 0.0, 0.0)]
+    ]
+];
+
+/// Depths of the shallow searches that predict a deep search, cheapest
+/// first. Roughly a quarter and a half of the deep depth, with the same
+/// parity as scores swing between odd and even depths. Deeper than
+/// MAX_DEPTH the shallow searches are as much shallower as at MAX_DEPTH.
+///
+/// Returns shallow depth of every check, None for checks the depth doesn't have, as it is
+/// below MIN_DEPTH or the shallow depth is no deeper than the one of the check before
+pub fn shallow_depths(depth: u32) -> [Option<u32>; CHECKS] {
+    let fitted = depth.min(MAX_DEPTH);
+    let shallow = [fitted / 4, fitted / 2].map(|shallow| shallow + (fitted - shallow) % 2 + depth - fitted);
+
+    let mut checks = [None; CHECKS];
+    for check in 0..CHECKS {
+        if depth >= MIN_DEPTH && (check == 0 || shallow[check] > shallow[check - 1]) {
+            checks[check] = Some(shallow[check]);
+        }
+    }
+    checks
+}
+
+/// Phase of the game from the number of empty tiles, 0 at the start
+pub fn phase(empties: u32) -> usize {
+    (60 - empties.min(60)) as usize * PHASES / 61
+}
+
+/// Checks of a search of depth with this many empty tiles, cheapest first
+///
+/// # Arguments
+/// *   cuts: Parameters fitted for the evaluator
+/// *   depth: Depth of the deep search
+/// *   empties: Number of empty tiles on the board
+///
+/// Returns shallow depth, threshold and parameters of every check
+pub fn checks(cuts: &Cuts, depth: u32, empties: u32) -> impl Iterator<Item = (u32, f32, Cut)> + '_ {
+    let cuts = &cuts[depth.min(MAX_DEPTH) as usize];
+    let phase = phase(empties);
+
+    shallow_depths(depth)
+        .into_iter()
+        .zip(cuts)
+        .zip(THRESHOLDS)
+        .filter_map(move |((shallow, cuts), threshold)| Some((shallow?, threshold, cuts[phase])))
+        .filter(|&(_, _, cut)| cut.a > 0.0)
+}
@@ -0,0 +1,69 @@
+use crate::game::{self, Board, Bits, Move};
+use super::moves::move_mask;
+
+/// Small, fast pseudo random number generator (xorshift64*). Not fit
+/// for anything secret, but the same seed always gives the same numbers,
+/// which makes random games reproducible.
+#[derive(Clone, Debug)]
+pub struct Random {
+    state: u64
+}
+
+impl Random {
+    /// # Arguments
+    /// *   seed: Any number, the same seed always gives the same numbers
+    pub fn new(seed: u64) -> Self {
+        // State must never be 0, mixing the seed also spreads out close seeds
+        Self { state: (seed ^ 0x9E3779B97F4A7C15).wrapping_mul(0xBF58476D1CE4E5B9) | 1 }
+    }
+
+    /// Next random number
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    /// Random number from 0 up to but not including n, n must not be 0
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Random valid move for the side to move, every move as likely as the others
+    ///
+    /// Returns move, a pass if the side has no moves, None if the game is over
+    pub fn pick_move(&mut self, board: &Board) -> Option<Move> {
+        self.weighted_move(board, |_| 1)
+    }
+
+    /// Random valid move for the side to move, each move as likely as its weight
+    ///
+    /// # Arguments
+    /// *   board: Board the move is picked on
+    /// *   weight: Weight of a move from its index, at least 1
+    ///
+    /// Returns move, a pass if the side has no moves, None if the game is over
+    pub fn weighted_move(&mut self, board: &Board, weight: impl Fn(usize) -> usize) -> Option<Move> {
+        let moves = move_mask(board, board.to_move);
+        if moves == 0 {
+            return (move_mask(board, !board.to_move) != 0).then_some(Move::Pass)
+        }
+
+        let mut pick = self.below(Bits(moves).map(&weight).sum());
+        Bits(moves).find(|&m| {
+            if pick < weight(m) { return true }
+            pick -= weight(m);
+            false
+        }).map(Move::Play) // Pick is below the total, so some move takes it
+    }
+
+    /// Plays a random valid move for the side to move, see pick_move
+    ///
+    /// Returns move that was played, None if the game is over
+    pub fn play_move(&mut self, board: &mut Board) -> Option<Move> {
+        let m = self.pick_move(board)?;
+        game::play_move(board, m);
+        Some(m)
+    }
+}
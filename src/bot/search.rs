@@ -1,79 +1,665 @@
-use crate::game::{Board, play, unplay};
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use super::moves::{generate_moves, heuristic_order};
-use super::evaluation::evaluate;
+use crate::game::{Board, Move, play, unplay, play_move, unplay_move};
+
+use super::moves::{generate_moves, move_mask, heuristic_order, MoveHistory};
+use super::evaluation::{Evaluator, Heuristic};
+use super::transposition::{Bound, TranspositionTable};
+use super::{Config, Limit};
+use super::probcut::{self, Cuts};
+
+// How many nodes are searched between checks of the clock
+const NODES_PER_TIME_CHECK: u64 = 1024;
+
+// Depth left from which moves are sorted by the replies they leave. Below it counting
+// them costs more than it saves, with the current evaluation it only breaks even here
+const MOBILITY_ORDER_DEPTH: u32 = 6;
+
+// Widest any score can be, one more than a loss and one less than a win
+const FULL_WINDOW: (i32, i32) = (i32::MIN + 1, i32::MAX - 1);
+
+// How far from the score of the previous depth the first window of the next depth reaches
+const ASPIRATION_WINDOW: i32 = 50;
+
+// Depths skipped by the extra threads of Lazy SMP. Extra thread n skips every other block of
+// SKIP_SIZE[n] depths, shifted by SKIP_PHASE[n] depths, so no two of them search the same depths
+const SKIP_SIZE: [u32; 20] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+const SKIP_PHASE: [u32; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+
+/// What a search found out about a board state
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Analysis {
+    /// Score of the best move for the side to move
+    pub score: i32,
+    /// Principal variation, the line both sides are expected to play, starting with the best move
+    pub pv: Vec<Move>,
+    /// Depth of the last completed search
+    pub depth: u32
+}
 
 /// Bot play function. Combines search and playing out move on board for bot.
-/// 
+///
+/// # Arguments
+/// *   board: Board which is evaluated
+/// *   depth: Depth left to search
+/// *   color: Side whose moves are being evaluated
+/// *   evaluator: Scores the boards at the end of each line
+/// *   tt: Table the results are kept in, reused from search to search
+///
+/// Returns vector of scores and moves, only the score of the first best move is exact,
+/// the others are upper bounds of their real score. Searches every line to the full
+/// depth, Multi-ProbCut is never used
+pub fn search<E: Evaluator + ?Sized>(board: &Board, depth: u32, color: bool, evaluator: &E, tt: &TranspositionTable) -> Vec<(i32, usize)> {
+    let mut moves = generate_moves(board, color);
+    heuristic_order(&mut moves);
+
+    tt.new_search();
+    let stop = AtomicBool::new(false);
+    Searcher::new(tt, &stop, evaluator, false).search_with_moves_inputted(board, depth, color, &moves, false, FULL_WINDOW)
+}
+
+/// search function but the exact score of every move is found, not just
+/// the best one. Slower, as no move can be cut off by a better sibling
+///
 /// # Arguments
 /// *   board: Board which is evaluated
 /// *   depth: Depth left to search
 /// *   color: Side whose moves are being evaluated
-/// 
+/// *   evaluator: Scores the boards at the end of each line
+/// *   tt: Table the results are kept in, reused from search to search
+///
 /// Returns vector of scores and moves
-pub fn search(board: &Board, depth: u32, color: bool) -> Vec<(i32, usize)> {
-    // TODO: Add support for iterative deepening
-    // TODO: Add support for a transposition table, likely Rc<RefCell<HashMap<board, i32>>>
+pub fn search_moves<E: Evaluator + ?Sized>(board: &Board, depth: u32, color: bool, evaluator: &E, tt: &TranspositionTable) -> Vec<(i32, usize)> {
     let mut moves = generate_moves(board, color);
     heuristic_order(&mut moves);
-    search_with_moves_inputted(board, depth, color, moves)
+
+    tt.new_search();
+    let stop = AtomicBool::new(false);
+    Searcher::new(tt, &stop, evaluator, false).search_with_moves_inputted(board, depth, color, &moves, true, FULL_WINDOW)
+}
+
+/// Iterative deepening search. Searches depth 1, then depth 2, and so on
+/// until time runs out, the moves of each depth are searched in the order
+/// of the scores from the previous depth so the best move is searched
+/// first. A depth that is cut off by the clock is thrown away.
+///
+/// # Arguments
+/// *   board: Board which is evaluated
+/// *   color: Side whose moves are being evaluated
+/// *   time: Time budget in milliseconds
+/// *   tt: Transposition table, kept from search to search
+///
+/// Returns best move of the last completed depth, None if there are no moves,
+/// boards are scored with the Heuristic evaluator
+pub fn iterative_search(board: &Board, color: bool, time: u64, tt: &TranspositionTable) -> Option<usize> {
+    let config = Config { limit: Limit::Time(time), ..Config::default() };
+
+    match analyze(board, color, &config, &Heuristic::default(), tt)?.pv.first() {
+        Some(&Move::Play(m)) => Some(m),
+        _ => None
+    }
+}
+
+/// iterative_search function but the score and expected line of the last
+/// completed depth are kept, not just the move. With a depth limit every
+/// depth up to it is searched, with a time limit it stops when time is up.
+/// Each depth starts with a narrow window around the score of the previous
+/// depth, which is only widened if the score turns out to be outside of it.
+///
+/// With more than one thread the extra threads search the same board at
+/// the same time (Lazy SMP). They only share the transposition table, and
+/// as each skips different depths they reach parts of the tree at different
+/// times, filling the table with results the others can use. Only the
+/// results of the main thread are kept, with one thread nothing is shared
+/// and the search is deterministic for a given table.
+///
+/// # Arguments
+/// *   board: Board which is evaluated
+/// *   color: Side whose moves are being evaluated
+/// *   config: Depth or time to search for, number of threads and whether Multi-ProbCut is used
+/// *   evaluator: Scores the boards at the end of each line
+/// *   tt: Table the results are kept in, reused from search to search
+///
+/// Returns analysis of the last completed depth, None if there are no moves
+pub fn analyze<E: Evaluator + ?Sized>(board: &Board, color: bool, config: &Config, evaluator: &E, tt: &TranspositionTable) -> Option<Analysis> {
+    let (deadline, max_depth) = match config.limit {
+        Limit::Depth(depth) => (None, depth),
+        Limit::Time(time) => (Some(Instant::now() + Duration::from_millis(time)), board.empty().count_ones())
+    };
+
+    // Entries of earlier searches are kept, but are the first to be replaced
+    tt.new_search();
+    let stop = AtomicBool::new(false); // Tells the extra threads that the main thread is done
+
+    if config.threads <= 1 {
+        return Searcher::new(tt, &stop, evaluator, config.probcut).iterate(board, color, max_depth, deadline, 0)
+    }
+
+    thread::scope(|scope| {
+        for thread in 1..config.threads {
+            let stop = &stop;
+            scope.spawn(move || Searcher::new(tt, stop, evaluator, config.probcut).iterate(board, color, max_depth, deadline, thread));
+        }
+
+        let analysis = Searcher::new(tt, &stop, evaluator, config.probcut).iterate(board, color, max_depth, deadline, 0);
+        stop.store(true, Ordering::Relaxed);
+        analysis
+    })
 }
 
-/// search function but moves are inputted
-fn search_with_moves_inputted(board: &Board, depth: u32, color: bool, moves: Vec<usize>) -> Vec<(i32, usize)> {
-    let mut board = board.clone();
+/// State carried through every node of a single search, one for each thread
+struct Searcher<'a, E: Evaluator + ?Sized> {
+    deadline: Option<Instant>, // Search is abandoned once this is passed
+    stop: &'a AtomicBool, // Search is abandoned once this is set
+    stopped: bool,
+    nodes: u64,
+    tt: &'a TranspositionTable,
+    evaluator: &'a E,
+    history: MoveHistory,
+    cuts: Option<&'a Cuts>, // Deep searches are cut when shallow searches predict them, None to never cut
+    pv: Vec<Vec<Move>>, // Best line found from each ply, built up on the way back to the root
+    root_pv: Vec<Move> // Best line of the last root search
+}
+
+impl<'a, E: Evaluator + ?Sized> Searcher<'a, E> {
+    fn new(tt: &'a TranspositionTable, stop: &'a AtomicBool, evaluator: &'a E, probcut: bool) -> Self {
+        Self {
+            deadline: None,
+            stop,
+            stopped: false,
+            nodes: 0,
+            tt,
+            evaluator,
+            history: MoveHistory::new(),
+            cuts: if probcut { evaluator.cuts() } else { None },
+            pv: vec![],
+            root_pv: vec![]
+        }
+    }
+
+    /// Iterative deepening loop of one thread. The main thread searches
+    /// every depth, each extra thread skips some of them, so that the
+    /// threads spread out over depths and finish at different times
+    ///
+    /// # Arguments
+    /// *   board: Board which is evaluated
+    /// *   color: Side whose moves are being evaluated
+    /// *   max_depth: Deepest depth that is searched
+    /// *   deadline: Time at which the search is abandoned, None to search every depth
+    /// *   thread: Number of the thread, 0 for the main thread
+    ///
+    /// Returns analysis of the last completed depth, None if there are no moves
+    fn iterate(&mut self, board: &Board, color: bool, max_depth: u32, deadline: Option<Instant>, thread: usize) -> Option<Analysis> {
+        let mut moves = generate_moves(board, color);
+        heuristic_order(&mut moves);
+
+        let mut analysis: Option<Analysis> = None;
+        let skipped = |depth: u32| {
+            let n = (thread + SKIP_SIZE.len() - 1) % SKIP_SIZE.len();
+            thread != 0 && (depth + SKIP_PHASE[n]) / SKIP_SIZE[n] % 2 == 1
+        };
+
+        // First depth of the main thread always completes, so there is always a move
+        for depth in (1..=max_depth.max(1)).filter(|&depth| !skipped(depth)) {
+            let mut evaluated_moves = match &analysis {
+                Some(analysis) => self.aspiration_search(board, depth, color, &moves, analysis.score),
+                None => self.search_with_moves_inputted(board, depth, color, &moves, false, FULL_WINDOW)
+            };
+            if self.stopped || evaluated_moves.is_empty() {
+                break
+            }
+
+            // Stable sort, moves with equal scores keep the order of the previous depth
+            evaluated_moves.sort_by_key(|&(score, _)| Reverse(score));
+            moves = evaluated_moves.iter().map(|&(_, m)| m).collect();
+            analysis = Some(Analysis { score: evaluated_moves[0].0, pv: self.root_pv.clone(), depth });
+
+            self.deadline = deadline;
+            self.history.age();
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break
+            }
+        }
+
+        analysis
+    }
+
+    /// Checks the clock and the stop flag every so often, once time is
+    /// up every node returns straight away and the results are garbage
+    fn out_of_time(&mut self) -> bool {
+        self.nodes += 1;
+
+        if !self.stopped && self.nodes.is_multiple_of(NODES_PER_TIME_CHECK) {
+            self.stopped = self.stop.load(Ordering::Relaxed) || self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        }
+
+        self.stopped
+    }
+
+    /// Sets the line from ply to the move followed by the line found from the next ply
+    fn update_pv(&mut self, ply: usize, m: Move) {
+        let (line, rest) = self.pv.split_at_mut(ply + 1);
+        line[ply].clear();
+        line[ply].push(m);
+        line[ply].extend_from_slice(&rest[0]);
+    }
+
+    /// search function but moves are inputted. Alpha is raised as better
+    /// moves are found, so every move after the first only has to prove
+    /// it is no better than the best so far, unless exact scores are asked for.
+    /// Once a move reaches beta the rest are not searched, unless the window is full.
+    fn search_with_moves_inputted(&mut self, board: &Board, depth: u32, color: bool, moves: &[usize], exact: bool, window: (i32, i32)) -> Vec<(i32, usize)> {
+        let mut board = *board;
+        let depth = if depth == 0 { 0 } else { depth - 1 };
+
+        let (mut alpha, beta) = window;
+
+        let mut evaluated_moves: Vec<(i32, usize)> = vec![];
+
+        for &m in moves {
+            let flips = play(&mut board, m, color);
+
+            let evaluation = if exact || evaluated_moves.is_empty() {
+                -self.search_inner(board, depth, 1, !color, -beta, -alpha)
+            } else {
+                let evaluation = -self.search_inner(board, depth, 1, !color, -alpha - 1, -alpha);
+
+                // Better than the best so far, the exact score is needed to compare against the rest
+                if evaluation > alpha && evaluation < beta {
+                    -self.search_inner(board, depth, 1, !color, -beta, -alpha)
+                } else {
+                    evaluation
+                }
+            };
+
+            unplay(&mut board, color, flips);
+
+            if self.stopped {
+                break
+            }
+
+            // First of the best moves keeps its line, same as the move that gets picked
+            if evaluated_moves.iter().all(|&(score, _)| evaluation > score) {
+                self.update_pv(0, Move::Play(m));
+                self.root_pv = self.pv[0].clone();
+
+                if !exact {
+                    alpha = alpha.max(evaluation);
+                }
+            }
+
+            evaluated_moves.push((evaluation, m));
+
+            // Above the window, it will be searched again anyway. With the full window
+            // the rest are still searched, as every move is wanted back
+            if evaluation >= beta && beta < FULL_WINDOW.1 {
+                break
+            }
+        }
+
+        evaluated_moves
+    }
+
+    /// search_with_moves_inputted function but with a window around a guess
+    /// of the score. A best score on or outside of the window is only a
+    /// bound, so the window is widened on that side and searched again.
+    ///
+    /// # Arguments
+    /// *   board: Board which is evaluated
+    /// *   depth: Depth left to search
+    /// *   color: Side whose moves are being evaluated
+    /// *   moves: Moves in the order they are searched
+    /// *   guess: Expected score, usually the score of the previous depth
+    ///
+    /// Returns vector of scores and moves
+    fn aspiration_search(&mut self, board: &Board, depth: u32, color: bool, moves: &[usize], guess: i32) -> Vec<(i32, usize)> {
+        let mut delta = ASPIRATION_WINDOW;
+        let mut window = (guess.saturating_sub(delta).max(FULL_WINDOW.0), guess.saturating_add(delta).min(FULL_WINDOW.1));
 
-    let beta = std::i32::MAX - 1;
-    let alpha = std::i32::MIN + 1;
+        loop {
+            let evaluated_moves = self.search_with_moves_inputted(board, depth, color, moves, false, window);
+            if self.stopped {
+                return evaluated_moves
+            }
 
-    let mut evaluated_moves = vec![];
+            let best = evaluated_moves.iter().map(|&(score, _)| score).max().unwrap_or(FULL_WINDOW.0);
+            delta = delta.saturating_mul(4);
 
-    if moves.is_empty() {
-        return vec![]
+            if best <= window.0 && window.0 > FULL_WINDOW.0 {
+                window.0 = best.saturating_sub(delta).max(FULL_WINDOW.0); // Failed low
+            } else if best >= window.1 && window.1 < FULL_WINDOW.1 {
+                window.1 = best.saturating_add(delta).min(FULL_WINDOW.1); // Failed high
+            } else {
+                return evaluated_moves
+            }
+        }
     }
 
-    for m in moves {
-        let flips = play(&mut board, m, color);
-        let evaluation = -search_inner(board.clone(), if depth == 0 { 0 } else { depth - 1 }, !color, -beta, -alpha);
-        unplay(&mut board, color, flips);
+    /// Multi-ProbCut. Shallow searches check whether the deep search
+    /// is very likely to fail high or low, each by searching with a window
+    /// moved as far past beta or alpha as the fitted error of its
+    /// prediction allows. The cheapest check goes first, nodes that are
+    /// predicted by any of them are not searched deep.
+    ///
+    /// # Arguments
+    /// *   board: Board which is evaluated
+    /// *   depth: Depth left to search
+    /// *   ply: Number of moves played since the root
+    /// *   color: Side whose moves are being evaluated
+    /// *   alpha: Best score for that side (worst for opponent)
+    /// *   beta: Worst score fot that side (best for opponent)
+    ///
+    /// Returns beta or alpha if the deep search is predicted to fail high or low, None otherwise
+    fn probcut(&mut self, board: Board, depth: u32, ply: usize, color: bool, alpha: i32, beta: i32) -> Option<i32> {
+        let cuts = self.cuts?;
+        for (shallow, threshold, cut) in probcut::checks(cuts, depth, board.empty().count_ones()) {
+            let margin = threshold * cut.sigma;
+
+            // Shallow score the deep score is predicted from, a * shallow + b, has to be past
+            let bound = |score: i32, margin: f32| ((score as f32 + margin - cut.b) / cut.a).round() as i32;
+
+            let high = bound(beta, margin).clamp(FULL_WINDOW.0 + 1, FULL_WINDOW.1);
+            if high < FULL_WINDOW.1 && self.search_inner(board, shallow, ply, color, high - 1, high) >= high {
+                return (!self.stopped).then_some(beta)
+            }
+
+            let low = bound(alpha, -margin).clamp(FULL_WINDOW.0, FULL_WINDOW.1 - 1);
+            if low > FULL_WINDOW.0 && self.search_inner(board, shallow, ply, color, low, low + 1) <= low {
+                return (!self.stopped).then_some(alpha)
+            }
+        }
 
-        evaluated_moves.push((evaluation, m));
+        None
     }
 
-    evaluated_moves
+    /// Principal Variation Search (NegaScout). The first move is expected
+    /// to be the best and is searched with the full window. Every other
+    /// move is only searched with a null window around alpha, which just
+    /// proves that it is no better and is a lot cheaper. If it turns out
+    /// to be better after all it is searched again with the full window.
+    /// Fail-soft, scores outside of alpha and beta are returned as they
+    /// are, which makes them tighter bounds for the table.
+    ///
+    /// # Arguments
+    /// *   board: Board which is evaluated
+    /// *   depth: Depth left to search
+    /// *   ply: Number of moves played since the root
+    /// *   color: Side whose moves are being evaluated
+    /// *   alpha: Best score for that side (worst for opponent)
+    /// *   beta: Worst score fot that side (best for opponent)
+    ///
+    /// Returns evaluation of board state, propagated from when depth reaches 0
+    fn search_inner(&mut self, mut board: Board, depth: u32, ply: usize, color: bool, mut alpha: i32, beta: i32) -> i32 {
+        if self.out_of_time() { return 0 }
+
+        while self.pv.len() <= ply + 1 {
+            self.pv.push(vec![]);
+        }
+        self.pv[ply].clear();
+
+        // A previous search of the same state can answer this one outright,
+        // otherwise its best move is still the best guess for which move to try first
+        let key = board.key(color);
+        let mut tt_move = None;
+        if let Some(entry) = self.tt.probe(key) {
+            if entry.depth >= depth {
+                match entry.bound {
+                    // On the principal variation the line is wanted too, which the table doesn't keep
+                    Bound::Exact if alpha + 1 == beta => return entry.score,
+                    Bound::Lower if entry.score >= beta => return entry.score,
+                    Bound::Upper if entry.score <= alpha => return entry.score,
+                    _ => {}
+                }
+            }
+            tt_move = entry.best_move;
+        }
+
+        let mut moves = generate_moves(&board, color);
+
+        // If the final depth is reached the evaluation of the board is propagated back
+        if depth == 0 { return self.evaluator.evaluate(&board, color) }
+
+        if moves.is_empty() {
+            // Neither side can move, the game is over
+            if move_mask(&board, !color) == 0 { return self.evaluator.evaluate(&board, color) }
+
+            // Passing is a ply like any other, the opponent moves again from the same board
+            let flips = play_move(&mut board, Move::Pass);
+            let evaluation = -self.search_inner(board, depth - 1, ply + 1, !color, -beta, -alpha);
+            unplay_move(&mut board, Move::Pass, flips);
+
+            self.update_pv(ply, Move::Pass);
+            return evaluation
+        }
+
+        // Only null windows are cut, the exact scores of the principal variation are kept.
+        // Checked before the moves are sorted, which is wasted on a node that is cut
+        if self.cuts.is_some() && alpha + 1 == beta {
+            if let Some(score) = self.probcut(board, depth, ply, color, alpha, beta) {
+                return score
+            }
+        }
+
+        self.history.order(&board, &mut moves, ply, color, depth >= MOBILITY_ORDER_DEPTH);
+
+        if let Some(position) = tt_move.and_then(|tt_move| moves.iter().position(|&m| m == tt_move)) {
+            moves[..=position].rotate_right(1); // Moves stored move to the front
+        }
+
+        let mut best = i32::MIN + 1;
+        let mut best_move = None;
+        let original_alpha = alpha;
+        for (n, m) in moves.into_iter().enumerate() {
+            let flips = play(&mut board, m, color); // Plays Move
+
+            let evaluation = if n == 0 {
+                -self.search_inner(board, depth - 1, ply + 1, !color, -beta, -alpha)
+            } else {
+                let evaluation = -self.search_inner(board, depth - 1, ply + 1, !color, -alpha - 1, -alpha);
+
+                // Move beat alpha, the null window only says that, not by how much
+                if evaluation > alpha && evaluation < beta {
+                    -self.search_inner(board, depth - 1, ply + 1, !color, -beta, -alpha)
+                } else {
+                    evaluation
+                }
+            };
+
+            unplay(&mut board, color, flips); // Un-plays Move
+
+            if self.stopped {
+                return 0 // Results are garbage, they must not end up in the table
+            }
+
+            if best_move.is_none() || evaluation > best {
+                best = evaluation;
+                best_move = Some(m);
+            }
+
+            // Checks if move is better than current best score
+            if evaluation > alpha {
+                alpha = evaluation;
+                self.update_pv(ply, Move::Play(m));
+            }
+
+            if evaluation >= beta {
+                // Move was too good, opponent will never allow it, rest can be pruned
+                self.history.cutoff(m, ply, color, depth);
+                self.tt.store(key, depth, Bound::Lower, best, best_move);
+                return best
+            }
+        }
+
+        let bound = if best > original_alpha { Bound::Exact } else { Bound::Upper };
+        self.tt.store(key, depth, bound, best, best_move);
+
+        // Returns best possible score of that route
+        best
+    }
 }
 
-/// Bot play function. Combines search and playing out move on board for bot.
-/// 
-/// # Arguments
-/// *   board: Board which is evaluated
-/// *   depth: Depth left to search
-/// *   color: Side whose moves are being evaluated
-/// *   alpha: Best score for that side (worst for opponent)
-/// *   beta: Worst score fot that side (best for opponent)
-/// 
-/// Returns evaluation of board state, propagated from when depth reaches 0
-fn search_inner(mut board: Board, depth: u32, color: bool, mut alpha: i32, beta: i32) -> i32 {
-    let mut moves = generate_moves(&board, color);
-    heuristic_order(&mut moves);
-    
-    // If the final depth is reached or no moves are possible the evaluation of the board is propagated back
-    if depth == 0 || moves.is_empty() { return evaluate(&board, color) }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Random;
+
+    /// Plain negamax to a fixed depth, nothing is cut off. Passes and
+    /// the end of the game are handled the same way search_inner does
+    fn negamax(board: &mut Board, depth: u32, color: bool) -> i32 {
+        let moves = generate_moves(board, color);
+        if depth == 0 || moves.is_empty() && move_mask(board, !color) == 0 {
+            return Heuristic::default().evaluate(board, color)
+        }
+
+        if moves.is_empty() {
+            let flips = play_move(board, Move::Pass);
+            let evaluation = -negamax(board, depth - 1, !color);
+            unplay_move(board, Move::Pass, flips);
+            return evaluation
+        }
+
+        moves
+            .into_iter()
+            .map(|m| {
+                let flips = play(board, m, color);
+                let evaluation = -negamax(board, depth - 1, !color);
+                unplay(board, color, flips);
+                evaluation
+            })
+            .max()
+            .unwrap() // There is a move
+    }
+
+    /// Real score of every move of the side to move, searched like the root is
+    fn move_scores(board: &Board, depth: u32) -> Vec<(i32, usize)> {
+        let (mut board, color) = (*board, board.to_move);
 
-    for m in moves {
-        let flips = play(&mut board, m, color); // Plays Move
-        let evaluation = -search_inner(board.clone(), depth - 1, !color, -beta, -alpha); // Evaluates recursively
-        unplay(&mut board, color, flips); // Un-plays Move
+        generate_moves(&board, color)
+            .into_iter()
+            .map(|m| {
+                let flips = play(&mut board, m, color);
+                let evaluation = -negamax(&mut board, depth.max(1) - 1, !color);
+                unplay(&mut board, color, flips);
+                (evaluation, m)
+            })
+            .collect()
+    }
+
+    /// Positions from random games where the side to move has moves, from the opening to late in the game
+    fn positions() -> Vec<Board> {
+        let mut random = Random::new(1);
+        let mut positions = vec![];
+
+        while positions.len() < 40 {
+            let mut board = Board::new();
+            let moves = 4 + positions.len() * 3 % 50;
+            for _ in 0..moves {
+                random.play_move(&mut board);
+            }
 
-        if evaluation >= beta {
-            // Move was too good, needs to be pruned
-            return beta
+            if move_mask(&board, board.to_move) != 0 {
+                positions.push(board);
+            }
         }
 
-        // Checks if move is better than current best score
-        alpha = alpha.max(evaluation)
+        positions
     }
 
-    // Returns best possible score of that route
-    alpha
+    /// Score the principal variation ends on, from the side of color
+    fn pv_score(board: &Board, pv: &[Move], color: bool) -> i32 {
+        let mut end = *board;
+        for &m in pv {
+            play_move(&mut end, m);
+        }
+
+        let score = Heuristic::default().evaluate(&end, end.to_move);
+        if end.to_move == color { score } else { -score }
+    }
+
+    #[test]
+    fn search_matches_negamax() {
+        for board in positions() {
+            for depth in 1..=4 {
+                let expected = move_scores(&board, depth);
+                let best = expected.iter().map(|&(score, _)| score).max();
+                let scores = search(&board, depth, board.to_move, &Heuristic::default(), &TranspositionTable::default());
+
+                // Only the first best move is exact, the rest are upper bounds
+                let top = scores.iter().map(|&(score, _)| score).max();
+                let &(score, m) = scores.iter().find(|&&(score, _)| Some(score) == top).unwrap();
+                assert_eq!(Some(score), best, "{} depth {depth}", board.position());
+                assert!(expected.contains(&(score, m)), "{} depth {depth}", board.position());
+                for (score, m) in scores {
+                    assert!(expected.iter().any(|&expected| expected.1 == m && expected.0 <= score), "{} depth {depth}", board.position());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn search_moves_matches_negamax() {
+        for board in positions() {
+            for depth in 1..=4 {
+                let mut expected = move_scores(&board, depth);
+                let mut scores = search_moves(&board, depth, board.to_move, &Heuristic::default(), &TranspositionTable::default());
+                expected.sort_unstable_by_key(|&(_, m)| m);
+                scores.sort_unstable_by_key(|&(_, m)| m);
+
+                assert_eq!(scores, expected, "{} depth {depth}", board.position());
+            }
+        }
+    }
+
+    // One table is kept for every depth of a board and each depth is searched twice,
+    // so later searches start from what the ones before them stored
+    #[test]
+    fn analyze_matches_negamax() {
+        for board in positions() {
+            let tt = TranspositionTable::default();
+
+            for depth in (1..=4).flat_map(|depth| [depth, depth]) {
+                let config = Config { limit: Limit::Depth(depth), ..Config::default() };
+                let expected = move_scores(&board, depth);
+                let best = expected.iter().map(|&(score, _)| score).max().unwrap(); // Positions have moves
+                let analysis = analyze(&board, board.to_move, &config, &Heuristic::default(), &tt).unwrap();
+
+                assert_eq!(analysis.score, best, "{} depth {depth}", board.position());
+                assert_eq!(analysis.depth, depth);
+                match analysis.pv[0] {
+                    Move::Play(m) => assert!(expected.contains(&(best, m)), "{} depth {depth}", board.position()),
+                    Move::Pass => panic!("root moves are never passes")
+                }
+                assert_eq!(pv_score(&board, &analysis.pv, board.to_move), best, "{} depth {depth}", board.position());
+            }
+        }
+    }
+
+    // A reused table gives exact scores to the full window nodes of the next search
+    #[test]
+    fn analyze_twice_on_one_table() {
+        let (board, tt) = (Board::new(), TranspositionTable::default());
+        let config = Config { limit: Limit::Depth(4), ..Config::default() };
+
+        let first = analyze(&board, board.to_move, &config, &Heuristic::default(), &tt).unwrap();
+        let second = analyze(&board, board.to_move, &config, &Heuristic::default(), &tt).unwrap();
+
+        assert_eq!(first.score, second.score);
+        assert_eq!(second.pv.len(), 4);
+    }
+
+    // Aspiration windows can widen all the way to the full window, which the cut check has to take
+    #[test]
+    fn probcut_on_the_full_window() {
+        let (board, tt, stop) = (Board::new(), TranspositionTable::default(), AtomicBool::new(false));
+        let evaluator = Heuristic::default();
+        let mut moves = generate_moves(&board, board.to_move);
+        heuristic_order(&mut moves);
+
+        let mut searcher = Searcher::new(&tt, &stop, &evaluator, true);
+        let evaluated_moves = searcher.search_with_moves_inputted(&board, 6, board.to_move, &moves, false, FULL_WINDOW);
+        assert_eq!(evaluated_moves.len(), moves.len());
+    }
 }
@@ -0,0 +1,112 @@
+//! Stable discs, pieces that can never be flipped again for the rest of the game
+
+const COLUMN_A: u64 = 0x0101010101010101;
+const COLUMN_H: u64 = COLUMN_A << 7;
+const ROW_8: u64 = 0xff;
+const ROW_1: u64 = ROW_8 << 56;
+const BORDER: u64 = COLUMN_A | COLUMN_H | ROW_8 | ROW_1;
+
+// Every line of the board in each of the 4 directions
+const ROWS: [u64; 8] = lines(0);
+const COLUMNS: [u64; 8] = lines(1);
+const DIAGONALS: [u64; 15] = lines(2); // A8 to H1 and the ones parallel to it
+const ANTI_DIAGONALS: [u64; 15] = lines(3); // H8 to A1 and the ones parallel to it
+
+/// Masks of every line in one direction, a tile is on the line
+/// its row, column or sum or difference of both puts it on
+const fn lines<const N: usize>(direction: usize) -> [u64; N] {
+    let mut lines = [0; N];
+    let mut i = 0;
+    while i < 64 {
+        let (row, column) = (i / 8, i % 8);
+        let line = match direction {
+            0 => row,
+            1 => column,
+            2 => column + 7 - row,
+            _ => column + row
+        };
+        lines[line] |= 1 << i;
+        i += 1;
+    }
+    lines
+}
+
+/// Every tile on a line that has no empty tiles left
+fn full_lines(occupied: u64, lines: &[u64]) -> u64 {
+    lines
+        .iter()
+        .filter(|&&line| occupied & line == line)
+        .fold(0, |acc, line| acc | line)
+}
+
+/// Lower bound on the discs of player that can never be flipped. A
+/// disc can only be flipped along a line it is in the middle of, so a
+/// disc is stable if along each of the 4 directions it is at the edge
+/// of the board, its line is full, or it is next to a stable disc of its
+/// own. Starting from the corners, stable discs are grown until no more
+/// are found. Discs that are stable for more subtle reasons are missed.
+///
+/// # Arguments
+/// *   player: Bitmap of the side whose stable discs are found
+/// *   opponent: Bitmap of other side
+///
+/// Returns bitmap of stable discs of player
+pub fn stable_discs(player: u64, opponent: u64) -> u64 {
+    let occupied = player | opponent;
+    let horizontal = full_lines(occupied, &ROWS) | COLUMN_A | COLUMN_H;
+    let vertical = full_lines(occupied, &COLUMNS) | ROW_8 | ROW_1;
+    let diagonal = full_lines(occupied, &DIAGONALS) | BORDER;
+    let anti_diagonal = full_lines(occupied, &ANTI_DIAGONALS) | BORDER;
+
+    let mut stable = 0;
+    loop {
+        // Shifted bits that wrap around to the other side of the board are not neighbours
+        let next = player
+            & (horizontal | (stable << 1 & !COLUMN_A) | (stable >> 1 & !COLUMN_H))
+            & (vertical | stable << 8 | stable >> 8)
+            & (diagonal | (stable << 9 & !COLUMN_A) | (stable >> 9 & !COLUMN_H))
+            & (anti_diagonal | (stable << 7 & !COLUMN_H) | (stable >> 7 & !COLUMN_A));
+
+        if next == stable {
+            return stable
+        }
+        stable = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Random;
+    use crate::bot::moves::move_mask;
+    use crate::game::{Board, Bits, bitboard_flips};
+
+    // Stable discs have to be a lower bound, no reply may flip one,
+    // and they keep their color until the end of the game
+    #[test]
+    fn stable_discs_are_never_flipped() {
+        let mut random = Random::new(1);
+
+        for _ in 0..200 {
+            let mut board = Board::new();
+            let mut stable = [0, 0]; // Stable discs found so far, by color
+
+            loop {
+                for color in [false, true] {
+                    let (player, opponent) = (board.bitboard(color), board.bitboard(!color));
+                    assert_eq!(player & stable[color as usize], stable[color as usize], "{}", board.position());
+
+                    let found = stable_discs(player, opponent);
+                    for m in Bits(move_mask(&board, !color)) {
+                        assert_eq!(bitboard_flips(opponent, player, m) & found, 0, "{} {m}", board.position());
+                    }
+                    stable[color as usize] |= found;
+                }
+
+                if random.play_move(&mut board).is_none() {
+                    break // Game over
+                }
+            }
+        }
+    }
+}
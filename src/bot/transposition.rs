@@ -0,0 +1,135 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+/// Default number of entries in the table (must be a power of 2), 16MB
+pub const DEFAULT_ENTRIES: usize = 1 << 20;
+
+// Layout of the packed data of an entry
+const DEPTH_SHIFT: u32 = 32;
+const BOUND_SHIFT: u32 = 40;
+const MOVE_SHIFT: u32 = 42;
+const GENERATION_SHIFT: u32 = 49;
+const NO_MOVE: u64 = 0x7f;
+const OCCUPIED: u64 = 1 << 63;
+
+/// What a stored score says about the real score of a board state
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact, // Score is the real score
+    Lower, // Search failed high, real score is at least this
+    Upper  // Search failed low, real score is at most this
+}
+
+/// Result of a search of one board state
+#[derive(Clone, Copy, Debug)]
+pub struct Entry {
+    pub key: u64,
+    pub depth: u32,
+    pub bound: Bound,
+    pub score: i32,
+    pub best_move: Option<usize>,
+    generation: u8
+}
+
+impl Entry {
+    /// Packs everything but the key into one word
+    fn pack(&self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2
+        };
+
+        self.score as u32 as u64
+            | (self.depth.min(u8::MAX as u32) as u64) << DEPTH_SHIFT
+            | bound << BOUND_SHIFT
+            | self.best_move.map_or(NO_MOVE, |m| m as u64) << MOVE_SHIFT
+            | (self.generation as u64) << GENERATION_SHIFT
+            | OCCUPIED
+    }
+
+    fn unpack(key: u64, data: u64) -> Self {
+        let bound = match data >> BOUND_SHIFT & 0b11 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper
+        };
+        let best_move = data >> MOVE_SHIFT & NO_MOVE;
+
+        Self {
+            key,
+            depth: (data >> DEPTH_SHIFT & 0xff) as u32,
+            bound,
+            score: data as u32 as i32,
+            best_move: (best_move != NO_MOVE).then_some(best_move as usize),
+            generation: (data >> GENERATION_SHIFT) as u8
+        }
+    }
+}
+
+/// Fixed size transposition table, indexed by the low bits of the
+/// Zobrist key of a board state. Each slot holds one entry, when two
+/// states land on the same slot the new one replaces the old one if it
+/// was searched at least as deep, or if the old one is left over from
+/// a previous search.
+///
+/// Shared by every thread of a search without locks. An entry is packed
+/// into one word, and stored next to its key XORed with that word. If
+/// two threads write a slot at once and the halves get mixed up, the key
+/// no longer matches and the entry is read as missing.
+pub struct TranspositionTable {
+    entries: Vec<[AtomicU64; 2]>, // Key ^ data, data
+    generation: AtomicU8
+}
+
+impl TranspositionTable {
+    /// # Arguments
+    /// *   size: Number of entries, rounded up to a power of 2
+    pub fn new(size: usize) -> Self {
+        Self {
+            entries: (0..size.next_power_of_two()).map(|_| Default::default()).collect(),
+            generation: AtomicU8::new(0)
+        }
+    }
+
+    fn index(&self, key: u64) -> usize {
+        key as usize & (self.entries.len() - 1)
+    }
+
+    /// Marks all current entries as old, so that they are
+    /// the first to be replaced during the next search
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Looks up a board state, None if it has not been stored
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        let [check, data] = &self.entries[self.index(key)];
+        let data = data.load(Ordering::Relaxed);
+
+        (data & OCCUPIED != 0 && check.load(Ordering::Relaxed) ^ data == key).then(|| Entry::unpack(key, data))
+    }
+
+    /// Stores the result of a search, may be dropped by the replacement scheme
+    pub fn store(&self, key: u64, depth: u32, bound: Bound, score: i32, best_move: Option<usize>) {
+        let [check, data] = &self.entries[self.index(key)];
+        let generation = self.generation.load(Ordering::Relaxed);
+
+        let old = data.load(Ordering::Relaxed);
+        let replace = old & OCCUPIED == 0 || {
+            let old = Entry::unpack(check.load(Ordering::Relaxed) ^ old, old);
+            old.key == key || old.generation != generation || depth >= old.depth
+        };
+
+        if replace {
+            let entry = Entry { key, depth, bound, score, best_move, generation }.pack();
+            check.store(key ^ entry, Ordering::Relaxed);
+            data.store(entry, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_ENTRIES)
+    }
+}
@@ -0,0 +1,167 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use othellotron::bot::{Config, Limit, Engine, Playout, Budget, Phases};
+use othellotron::game::Board;
+
+/// Who makes the moves for one color
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Player {
+    Human,
+    Bot
+}
+
+/// How much is printed while the game is played
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Output {
+    Color, // Colored board after every move
+    Plain, // Board without colors, for terminals and files that can't show them
+    Quiet  // Only moves and the result, no board
+}
+
+/// Which evaluation the bot searches with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Eval {
+    Heuristic, // Hand written evaluation
+    Pattern    // Pattern tables, from the weights file if there is one
+}
+
+/// Settings for the game, read from the command line
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub white: Player,
+    pub black: Player,
+    pub config: Config,
+    pub eval: Eval,
+    pub weights: Option<String>, // File the pattern weights are read from
+    pub phases: Phases, // Where the heuristic switches between opening, midgame and endgame weights
+    pub position: Board,
+    pub random_moves: u32, // Random moves played from position before the game starts
+    pub seed: u64,
+    pub output: Output
+}
+
+/// Text printed by --help
+pub const USAGE: &str = "\
+Usage: othellotron [OPTIONS]
+
+Options:
+  --white PLAYER      Who plays white, human or bot [default: human]
+  --black PLAYER      Who plays black, human or bot [default: bot]
+  --engine ENGINE     How the bot picks moves, alphabeta or mcts [default: alphabeta]
+  --depth N           Bot searches N plies deep
+  --time MS           Bot thinks for MS milliseconds per move [default: 2000]
+  --iterations N      MCTS bot plays N playouts per move instead of thinking for a time
+  --exploration C     MCTS exploration constant [default: 1.41]
+  --playout PLAYOUT   MCTS playouts, random or heuristic [default: random]
+  --probcut on|off    Cut deep searches that shallow searches predict [default: off]
+  --threads N         Bot searches on N threads [default: 1]
+  --eval EVAL         Evaluation of the search, heuristic or pattern [default: heuristic]
+  --weights FILE      Pattern weights written by the tuner, picks the pattern evaluation
+  --phases MID,END    Empty tiles at which the heuristic's midgame and endgame weights
+                      take over [default: 40,20]
+  --position TEXT     Starting position, 64 tiles from A8 to H1 (W, B or -)
+                      followed by the side to move (W or B)
+  --random N          Play N random moves before the game starts [default: 0]
+  --seed N            Seed for the random moves [default: current time]
+  --output STYLE      color, plain (no colors) or quiet (no board) [default: color]
+  -h, --help          Print this text";
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            white: Player::Human,
+            black: Player::Bot,
+            config: Config::default(),
+            eval: Eval::Heuristic,
+            weights: None,
+            phases: Phases::default(),
+            position: Board::new(),
+            random_moves: 0,
+            seed: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_nanos() as u64),
+            output: Output::Color
+        }
+    }
+}
+
+/// Reads options from command line arguments, without the program name
+///
+/// Returns options, None if help was asked for, or a message saying which argument is wrong
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(None)
+        }
+
+        let value = args.next().ok_or(format!("{arg} needs a value"))?;
+        let invalid = || format!("\"{value}\" is not a valid value for {arg}");
+
+        match arg.as_str() {
+            "--white" => options.white = parse_player(&value).ok_or_else(invalid)?,
+            "--black" => options.black = parse_player(&value).ok_or_else(invalid)?,
+            "--engine" => options.config.engine = match value.as_str() {
+                "alphabeta" => Engine::AlphaBeta,
+                "mcts" => Engine::Mcts,
+                _ => return Err(invalid())
+            },
+            "--depth" => options.config.limit = Limit::Depth(value.parse().ok().filter(|&depth| depth > 0).ok_or_else(invalid)?),
+            "--time" => {
+                let time = value.parse().ok().filter(|&time| time > 0).ok_or_else(invalid)?;
+                options.config.limit = Limit::Time(time);
+                options.config.mcts.budget = Budget::Time(time);
+            },
+            "--iterations" => options.config.mcts.budget = Budget::Iterations(value.parse().ok().filter(|&iterations| iterations > 0).ok_or_else(invalid)?),
+            "--exploration" => options.config.mcts.exploration = value.parse().ok().filter(|&c: &f32| c >= 0.0).ok_or_else(invalid)?,
+            "--playout" => options.config.mcts.playout = match value.as_str() {
+                "random" => Playout::Random,
+                "heuristic" => Playout::Heuristic,
+                _ => return Err(invalid())
+            },
+            "--probcut" => options.config.probcut = match value.as_str() {
+                "on" => true,
+                "off" => false,
+                _ => return Err(invalid())
+            },
+            "--threads" => options.config.threads = value.parse().ok().filter(|&threads| threads > 0).ok_or_else(invalid)?,
+            "--eval" => options.eval = match value.as_str() {
+                "heuristic" => Eval::Heuristic,
+                "pattern" => Eval::Pattern,
+                _ => return Err(invalid())
+            },
+            "--weights" => {
+                options.eval = Eval::Pattern;
+                options.weights = Some(value);
+            },
+            "--phases" => options.phases = value
+                .split_once(',')
+                .and_then(|(midgame, endgame)| Some(Phases { midgame: midgame.trim().parse().ok()?, endgame: endgame.trim().parse().ok()? }))
+                .filter(|phases| phases.midgame <= 60 && phases.midgame > phases.endgame)
+                .ok_or_else(invalid)?,
+            "--position" => options.position = value.parse().map_err(|e| format!("{}: {e}", invalid()))?,
+            "--random" => options.random_moves = value.parse().map_err(|_| invalid())?,
+            "--seed" => options.seed = value.parse().map_err(|_| invalid())?,
+            "--output" => options.output = match value.as_str() {
+                "color" => Output::Color,
+                "plain" => Output::Plain,
+                "quiet" => Output::Quiet,
+                _ => return Err(invalid())
+            },
+            _ => return Err(format!("Unknown option {arg}"))
+        }
+    }
+
+    options.config.mcts.seed = options.seed; // Same seed, so that games can be played again
+    Ok(Some(options))
+}
+
+fn parse_player(value: &str) -> Option<Player> {
+    match value.to_ascii_lowercase().as_str() {
+        "human" => Some(Player::Human),
+        "bot" => Some(Player::Bot),
+        _ => None
+    }
+}
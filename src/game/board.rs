@@ -1,46 +1,99 @@
-use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
+use std::str::FromStr;
 use std::hash::{Hash, Hasher};
 
 use colored::Colorize;
-use crate::bot::moves::generate_moves;
-
-type Piece = Option<bool>;
-
-/// Board data type, uses multiple channels to not have 
-/// to redundantly iterate of main board. Future versions 
-/// of this bot might see this data structure converted to 
-/// 2 u64's as bitmaps. Despite bitmaps being much more memory 
-/// efficient I believe this code is more readable and the 
-/// current efficiency of the bot is sufficient for my project 
-/// goals.
-/// 
+use crate::bot::moves::move_mask;
+use super::{PIECE_KEYS, SIDE_KEY};
+
+/// Board data type, stored as 2 u64's used as bitmaps, one for each
+/// color. Bit i of a bitmap is set when that color has a piece on
+/// index i of the main board (index 0 is A8, index 63 is H1). Copying
+/// the board is just copying a few integers, which is what makes search
+/// cheap, and moves are found and played with shifts and masks
+/// instead of walking the board tile by tile. A Zobrist hash of the
+/// pieces is kept up to date by every insert, remove and flip. The
+/// side to move is tracked on the board, so that a side passing is
+/// part of the state rather than something the caller has to remember.
+///
 /// Takes no parameters while initializing.
-#[derive(Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Board {
-    pub pieces: [Piece; 64],
-    pub color_pieces: HashMap<bool, HashSet<usize>>,
-    pub piece_count: HashMap<bool, u8>,
-    pub prev_color: bool
+    /// Bitmap of white pieces
+    pub white: u64,
+    /// Bitmap of black pieces
+    pub black: u64,
+    /// Color of side whose turn it is
+    pub to_move: bool,
+    /// Zobrist hash of the pieces, does not include side to move
+    pub hash: u64
 }
 
 impl Board {
+    /// Starting position of the game
     pub fn new() -> Self {
-        Self {
-            pieces: [
-                None, None, None, None, None, None, None, None,
-                None, None, None, None, None, None, None, None,
-                None, None, None, None, None, None, None, None,
-                None, None, None, Some(true), Some(false), None, None, None,
-                None, None, None, Some(false), Some(true), None, None, None,
-                None, None, None, None, None, None, None, None,
-                None, None, None, None, None, None, None, None,
-                None, None, None, None, None, None, None, None
-            ],
-            color_pieces: HashMap::from([(true, HashSet::from([27, 36])), (false, HashSet::from([28, 35]))]),
-            piece_count: HashMap::from([(true, 2), (false, 2)]),
-            prev_color: false
+        let mut board = Self {
+            white: 0,
+            black: 0,
+            to_move: true, // White moves first
+            hash: 0
+        };
+
+        for (i, color) in [(27, true), (36, true), (28, false), (35, false)] {
+            board.insert(i, color);
         }
+
+        board
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Implementation of helper functions for reading the board
+impl Board {
+    /// Bitmap of all pieces of one color
+    pub fn bitboard(&self, color: bool) -> u64 {
+        if color { self.white } else { self.black }
+    }
+
+    /// Bitmap of every tile that has a piece on it
+    pub fn occupied(&self) -> u64 {
+        self.white | self.black
+    }
+
+    /// Bitmap of every tile that is empty
+    pub fn empty(&self) -> u64 {
+        !self.occupied()
+    }
+
+    /// Color of piece on tile, None if tile is empty
+    pub fn piece(&self, i: usize) -> Option<bool> {
+        if self.white >> i & 1 == 1 {
+            Some(true)
+        } else if self.black >> i & 1 == 1 {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Number of pieces of one color on the board
+    pub fn piece_count(&self, color: bool) -> u8 {
+        self.bitboard(color).count_ones() as u8
+    }
+
+    /// Zobrist key of the board with a side to move,
+    /// used to look the state up in a transposition table
+    pub fn key(&self, color: bool) -> u64 {
+        if color { self.hash ^ SIDE_KEY } else { self.hash }
+    }
+
+    fn bitboard_mut(&mut self, color: bool) -> &mut u64 {
+        if color { &mut self.white } else { &mut self.black }
     }
 }
 
@@ -48,69 +101,130 @@ impl Board {
 impl Board {
     /// Inserts piece onto board
     pub fn insert(&mut self, i: usize, color: bool) {
-        self.pieces[i] = Some(color); // Adding piece to main board
-
-        // Adding piece to color specific storage
-        self.color_pieces
-            .get_mut(&color)
-            .unwrap()
-            .insert(i);
-
-        // Updating piece count
-        *self.piece_count
-            .get_mut(&color)
-            .unwrap() += 1;
-    } 
+        *self.bitboard_mut(color) |= 1 << i;
+        self.hash ^= PIECE_KEYS[color as usize][i];
+    }
 
     /// Removes piece from board
     pub fn remove(&mut self, i: usize, color: bool) {
-        self.pieces[i] = None; // Removing piece from main board
-
-        // Removing piece from color specific storage
-        self.color_pieces
-            .get_mut(&color)
-            .unwrap()
-            .remove(&i);
-
-        // Updating piece count
-        *self.piece_count
-            .get_mut(&color)
-            .unwrap() -= 1;
-    } 
+        *self.bitboard_mut(color) &= !(1 << i);
+        self.hash ^= PIECE_KEYS[color as usize][i];
+    }
 
-    /// Flips color of piece on board, 
+    /// Flips color of piece on board,
     /// nothing happens if there isn't a piece
     pub fn flip(&mut self, i: usize) {
-        // Determine initial color
-        let color = match self.pieces[i] {
-            Some(color) => color,
-            None => return eprintln!("No pieces on tile to flip")
+        if self.occupied() >> i & 1 == 0 {
+            return eprintln!("No pieces on tile to flip")
+        }
+
+        // Piece is on exactly one of the bitmaps, toggling both moves it across
+        self.white ^= 1 << i;
+        self.black ^= 1 << i;
+
+        // Same goes for the hash, one key goes out and the other comes in
+        self.hash ^= PIECE_KEYS[0][i] ^ PIECE_KEYS[1][i];
+    }
+}
+
+/// Iterator over the indices of the set bits of a bitmap, lowest index first
+pub struct Bits(pub u64);
+
+impl Iterator for Bits {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None
+        }
+
+        let i = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1; // Clears lowest set bit
+        Some(i)
+    }
+}
+
+// Implementing hash for board state, the Zobrist hash already identifies the pieces
+impl Hash for Board {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash.hash(state);
+        self.to_move.hash(state)
+    }
+}
+
+// **** Shifts used to walk bitmaps across the board ****
+const NOT_A_FILE: u64 = 0xfefefefefefefefe; // Every tile except column A
+const NOT_H_FILE: u64 = 0x7f7f7f7f7f7f7f7f; // Every tile except column H
+
+/// All 8 directions a line of flips can run in, as offsets on the main board
+pub const DIRECTIONS: [i8; 8] = [-9, -8, -7, -1, 1, 7, 8, 9];
+
+/// Shifts every piece of a bitmap one tile in a direction, pieces
+/// that would wrap around to the other side of the board are dropped
+///
+/// # Arguments
+/// *   bitboard: Bitmap to shift
+/// *   direction: One of DIRECTIONS
+pub fn shift(bitboard: u64, direction: i8) -> u64 {
+    match direction {
+        -9 => (bitboard >> 9) & NOT_H_FILE,
+        -8 => bitboard >> 8,
+        -7 => (bitboard >> 7) & NOT_A_FILE,
+        -1 => (bitboard >> 1) & NOT_H_FILE,
+        1 => (bitboard << 1) & NOT_A_FILE,
+        7 => (bitboard << 7) & NOT_H_FILE,
+        8 => bitboard << 8,
+        9 => (bitboard << 9) & NOT_A_FILE,
+        _ => unreachable!("Invalid direction {direction}")
+    }
+}
+
+impl Board {
+    /// Writes the board as text that can be read back with parse. One
+    /// character per tile from A8 to H1, W for white, B for black and -
+    /// for empty, followed by W or B for the side to move
+    pub fn position(&self) -> String {
+        let mut position: String = (0..64)
+            .map(|i| match self.piece(i) {
+                Some(true) => 'W',
+                Some(false) => 'B',
+                None => '-'
+            })
+            .collect();
+
+        position.push(if self.to_move { 'W' } else { 'B' });
+        position
+    }
+}
+
+// Implementing parsing for board, the text written by Board::position.
+// Whitespace is ignored so the tiles can be split up into rows
+impl FromStr for Board {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chars: Vec<char> = s.chars().filter(|c| !c.is_whitespace()).collect();
+        if chars.len() != 65 {
+            return Err(format!("position has {} characters, expected 64 tiles and the side to move", chars.len()))
+        }
+
+        let mut board = Self { white: 0, black: 0, to_move: true, hash: 0 };
+        for (i, c) in chars[..64].iter().enumerate() {
+            match c.to_ascii_uppercase() {
+                'W' => board.insert(i, true),
+                'B' => board.insert(i, false),
+                '-' => {},
+                c => return Err(format!("\"{c}\" is not a tile, expected W, B or -"))
+            }
+        }
+
+        board.to_move = match chars[64].to_ascii_uppercase() {
+            'W' => true,
+            'B' => false,
+            c => return Err(format!("\"{c}\" is not a side to move, expected W or B"))
         };
 
-        // Flips color on main board
-        self.pieces[i] = Some(!color);
-
-        // Update piece on original color storage
-        self.color_pieces
-            .get_mut(&color)
-            .unwrap()
-            .remove(&i);
-
-        // Update piece count
-        *self.piece_count
-            .get_mut(&color)
-            .unwrap() -= 1;
-
-        // Update piece on target color storage
-        self.color_pieces
-            .get_mut(&!color)
-            .unwrap()
-            .insert(i);
-
-        // Update piece count
-        *self.piece_count
-            .get_mut(&!color)
-            .unwrap() += 1;
+        Ok(board)
     }
 }
 
@@ -125,389 +239,68 @@ impl Display for Board {
           ...
           +---+---+---+---+---+---+---+---+
         */
-        
+
         let mut board = String::from("\n    A   B   C   D   E   F   G   H\n  +---+---+---+---+---+---+---+---+\n");
-        let moves = generate_moves(self, !self.prev_color);
-        
+        let moves = move_mask(self, self.to_move);
+
         for row in 0..8 {
             board.push_str(&format!("{} |", 8 - row));
             for column in 0..8 {
-                let symbol= match self.pieces[row*8 + column] {
-                    Some(color) => { 
-                        if color { 
+                let symbol= match self.piece(row*8 + column) {
+                    Some(color) => {
+                        if color {
                             "W".white()
-                        } else { 
+                        } else {
                             "B".black()
-                        } 
+                        }
                     },
                     None => {
-                        if moves.contains(&(row*8 + column)) {
+                        if moves >> (row*8 + column) & 1 == 1 {
                             "X".magenta()
                         } else {
                             " ".clear()
                         }
                     }
                 };
-                
+
                 board.push_str(&format!(" {} |", symbol));
             }
             board.push_str("\n  +---+---+---+---+---+---+---+---+\n")
         }
 
         write!(f, "{board}")?;
-        write!(f, "Score (White:Black): {}:{}", self.piece_count[&true], self.piece_count[&false])?;
+        write!(f, "Score (White:Black): {}:{}", self.piece_count(true), self.piece_count(false))?;
         Ok(())
     }
 }
 
-// Implementing clone for board state
-impl Clone for Board {
-    fn clone(&self) -> Self {
-        Self {
-            pieces: self.pieces,
-            color_pieces: self.color_pieces.clone(),
-            piece_count: self.piece_count.clone(),
-            prev_color: self.prev_color
-        }
-    }
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Random;
+    use crate::game::{play_move, unplay_move};
 
-// Implementing hash for board state (For transposition table hashmap)
-impl Hash for Board {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.pieces.hash(state);
-        self.prev_color.hash(state)
-    }
-}
+    // The hash of a parsed board is built from scratch, so it has to
+    // match the one kept up to date move by move
+    #[test]
+    fn position_round_trip() {
+        let mut random = Random::new(1);
+
+        for _ in 0..100 {
+            let mut board = Board::new();
 
+            loop {
+                assert_eq!(board.position().parse::<Board>(), Ok(board));
 
-// **** All possible values for diagonals, rows and column ****
-const P_DIAGONALS: [&[usize]; 15] = [
-    &[0],
-    &[8, 1],
-    &[16, 9, 2],
-    &[24, 17, 10, 3],
-    &[32, 25, 18, 11, 4],
-    &[40, 33, 26, 19, 12, 5],
-    &[48, 41, 34, 27, 20, 13, 6],
-    &[56, 49, 42, 35, 28, 21, 14, 7],
-    &[57, 50, 43, 36, 29, 22, 15],
-    &[58, 51, 44, 37, 30, 23],
-    &[59, 52, 45, 38, 31],
-    &[60, 53, 46, 39],
-    &[61, 54, 47],
-    &[62, 55],
-    &[63]
-];
-
-const N_DIAGONALS: [&[usize]; 15] = [
-    &[56],
-    &[48, 57],
-    &[40, 49, 58],
-    &[32, 41, 50, 59],
-    &[24, 33, 42, 51, 60],
-    &[16, 25, 34, 43, 52, 61],
-    &[8, 17, 26, 35, 44, 53, 62],
-    &[0, 9, 18, 27, 36, 45, 54, 63],
-    &[1, 10, 19, 28, 37, 46, 55],
-    &[2, 11, 20, 29, 38, 47],
-    &[3, 12, 21, 30, 39],
-    &[4, 13, 22, 31],
-    &[5, 14, 23],
-    &[6, 15],
-    &[7]
-
-];
-
-const ROWS: [&[usize]; 8] = [
-    &[0, 1, 2, 3, 4, 5, 6, 7],
-    &[8, 9, 10, 11, 12, 13, 14, 15],
-    &[16, 17, 18, 19, 20, 21, 22, 23],
-    &[24, 25, 26, 27, 28, 29, 30, 31],
-    &[32, 33, 34, 35, 36, 37, 38, 39],
-    &[40, 41, 42, 43, 44, 45, 46, 47],
-    &[48, 49, 50, 51, 52, 53, 54, 55],
-    &[56, 57, 58, 59, 60, 61, 62, 63]
-];
-
-const COLUMNS: [&[usize]; 8] = [
-    &[0, 8, 16, 24, 32, 40, 48, 56],
-    &[1, 9, 17, 25, 33, 41, 49, 57],
-    &[2, 10, 18, 26, 34, 42, 50, 58],
-    &[3, 11, 19, 27, 35, 43, 51, 59],
-    &[4, 12, 20, 28, 36, 44, 52, 60],
-    &[5, 13, 21, 29, 37, 45, 53, 61],
-    &[6, 14, 22, 30, 38, 46, 54, 62],
-    &[7, 15, 23, 31, 39, 47, 55, 63],
-];
-
-
-// **** Large lookup tables for every position of the board ****
-// TODO: Potentially find different solution, not absolutely necessary
-pub const POSITIVE_DIAGONAL_LOOKUP: [&[usize]; 64] = [
-    P_DIAGONALS[0],
-    P_DIAGONALS[1],
-    P_DIAGONALS[2],
-    P_DIAGONALS[3],
-    P_DIAGONALS[4],
-    P_DIAGONALS[5],
-    P_DIAGONALS[6],
-    P_DIAGONALS[7],
-    P_DIAGONALS[1],
-    P_DIAGONALS[2],
-    P_DIAGONALS[3],
-    P_DIAGONALS[4],
-    P_DIAGONALS[5],
-    P_DIAGONALS[6],
-    P_DIAGONALS[7],
-    P_DIAGONALS[8],
-    P_DIAGONALS[2],
-    P_DIAGONALS[3],
-    P_DIAGONALS[4],
-    P_DIAGONALS[5],
-    P_DIAGONALS[6],
-    P_DIAGONALS[7],
-    P_DIAGONALS[8],
-    P_DIAGONALS[9],
-    P_DIAGONALS[3],
-    P_DIAGONALS[4],
-    P_DIAGONALS[5],
-    P_DIAGONALS[6],
-    P_DIAGONALS[7],
-    P_DIAGONALS[8],
-    P_DIAGONALS[9],
-    P_DIAGONALS[10],
-    P_DIAGONALS[4],
-    P_DIAGONALS[5],
-    P_DIAGONALS[6],
-    P_DIAGONALS[7],
-    P_DIAGONALS[8],
-    P_DIAGONALS[9],
-    P_DIAGONALS[10],
-    P_DIAGONALS[11],
-    P_DIAGONALS[5],
-    P_DIAGONALS[6],
-    P_DIAGONALS[7],
-    P_DIAGONALS[8],
-    P_DIAGONALS[9],
-    P_DIAGONALS[10],
-    P_DIAGONALS[11],
-    P_DIAGONALS[12],
-    P_DIAGONALS[6],
-    P_DIAGONALS[7],
-    P_DIAGONALS[8],
-    P_DIAGONALS[9],
-    P_DIAGONALS[10],
-    P_DIAGONALS[11],
-    P_DIAGONALS[12],
-    P_DIAGONALS[13],
-    P_DIAGONALS[7],
-    P_DIAGONALS[8],
-    P_DIAGONALS[9],
-    P_DIAGONALS[10],
-    P_DIAGONALS[11],
-    P_DIAGONALS[12],
-    P_DIAGONALS[13],
-    P_DIAGONALS[14],
-];
-
-pub const NEGATIVE_DIAGONAL_LOOKUP: [&[usize]; 64] = [
-    N_DIAGONALS[7], 
-    N_DIAGONALS[8], 
-    N_DIAGONALS[9], 
-    N_DIAGONALS[10], 
-    N_DIAGONALS[11],
-    N_DIAGONALS[12],
-    N_DIAGONALS[13], 
-    N_DIAGONALS[14],
-    N_DIAGONALS[6],
-    N_DIAGONALS[7],
-    N_DIAGONALS[8],
-    N_DIAGONALS[9], 
-    N_DIAGONALS[10], 
-    N_DIAGONALS[11], 
-    N_DIAGONALS[12], 
-    N_DIAGONALS[13],
-    N_DIAGONALS[5],
-    N_DIAGONALS[6],
-    N_DIAGONALS[7],
-    N_DIAGONALS[8],
-    N_DIAGONALS[9], 
-    N_DIAGONALS[10], 
-    N_DIAGONALS[11], 
-    N_DIAGONALS[12],
-    N_DIAGONALS[4],
-    N_DIAGONALS[5],
-    N_DIAGONALS[6],
-    N_DIAGONALS[7],
-    N_DIAGONALS[8],
-    N_DIAGONALS[9], 
-    N_DIAGONALS[10], 
-    N_DIAGONALS[11],
-    N_DIAGONALS[3],
-    N_DIAGONALS[4],
-    N_DIAGONALS[5],
-    N_DIAGONALS[6],
-    N_DIAGONALS[7],
-    N_DIAGONALS[8],
-    N_DIAGONALS[9], 
-    N_DIAGONALS[10],
-    N_DIAGONALS[2],
-    N_DIAGONALS[3],
-    N_DIAGONALS[4],
-    N_DIAGONALS[5],
-    N_DIAGONALS[6],
-    N_DIAGONALS[7],
-    N_DIAGONALS[8],
-    N_DIAGONALS[9],
-    N_DIAGONALS[1],
-    N_DIAGONALS[2],
-    N_DIAGONALS[3],
-    N_DIAGONALS[4],
-    N_DIAGONALS[5],
-    N_DIAGONALS[6],
-    N_DIAGONALS[7],
-    N_DIAGONALS[8],
-    N_DIAGONALS[0],
-    N_DIAGONALS[1],
-    N_DIAGONALS[2],
-    N_DIAGONALS[3],
-    N_DIAGONALS[4],
-    N_DIAGONALS[5],
-    N_DIAGONALS[6],
-    N_DIAGONALS[7],
-];
-
-pub const ROW_LOOKUP: [&[usize]; 64] = [
-    ROWS[0],
-    ROWS[0],
-    ROWS[0],
-    ROWS[0],
-    ROWS[0],
-    ROWS[0],
-    ROWS[0],
-    ROWS[0],
-    ROWS[1],
-    ROWS[1],
-    ROWS[1],
-    ROWS[1],
-    ROWS[1],
-    ROWS[1],
-    ROWS[1],
-    ROWS[1],
-    ROWS[2],
-    ROWS[2],
-    ROWS[2],
-    ROWS[2],
-    ROWS[2],
-    ROWS[2],
-    ROWS[2],
-    ROWS[2],
-    ROWS[3],
-    ROWS[3],
-    ROWS[3],
-    ROWS[3],
-    ROWS[3],
-    ROWS[3],
-    ROWS[3],
-    ROWS[3],
-    ROWS[4],
-    ROWS[4],
-    ROWS[4],
-    ROWS[4],
-    ROWS[4],
-    ROWS[4],
-    ROWS[4],
-    ROWS[4],
-    ROWS[5],
-    ROWS[5],
-    ROWS[5],
-    ROWS[5],
-    ROWS[5],
-    ROWS[5],
-    ROWS[5],
-    ROWS[5],
-    ROWS[6],
-    ROWS[6],
-    ROWS[6],
-    ROWS[6],
-    ROWS[6],
-    ROWS[6],
-    ROWS[6],
-    ROWS[6],
-    ROWS[7],
-    ROWS[7],
-    ROWS[7],
-    ROWS[7],
-    ROWS[7],
-    ROWS[7],
-    ROWS[7],
-    ROWS[7],
-];
-
-pub const COLUMN_LOOKUP: [&[usize]; 64] = [
-    COLUMNS[0],
-    COLUMNS[1],
-    COLUMNS[2],
-    COLUMNS[3],
-    COLUMNS[4],
-    COLUMNS[5],
-    COLUMNS[6],
-    COLUMNS[7],
-    COLUMNS[0],
-    COLUMNS[1],
-    COLUMNS[2],
-    COLUMNS[3],
-    COLUMNS[4],
-    COLUMNS[5],
-    COLUMNS[6],
-    COLUMNS[7],
-    COLUMNS[0],
-    COLUMNS[1],
-    COLUMNS[2],
-    COLUMNS[3],
-    COLUMNS[4],
-    COLUMNS[5],
-    COLUMNS[6],
-    COLUMNS[7],
-    COLUMNS[0],
-    COLUMNS[1],
-    COLUMNS[2],
-    COLUMNS[3],
-    COLUMNS[4],
-    COLUMNS[5],
-    COLUMNS[6],
-    COLUMNS[7],
-    COLUMNS[0],
-    COLUMNS[1],
-    COLUMNS[2],
-    COLUMNS[3],
-    COLUMNS[4],
-    COLUMNS[5],
-    COLUMNS[6],
-    COLUMNS[7],
-    COLUMNS[0],
-    COLUMNS[1],
-    COLUMNS[2],
-    COLUMNS[3],
-    COLUMNS[4],
-    COLUMNS[5],
-    COLUMNS[6],
-    COLUMNS[7],
-    COLUMNS[0],
-    COLUMNS[1],
-    COLUMNS[2],
-    COLUMNS[3],
-    COLUMNS[4],
-    COLUMNS[5],
-    COLUMNS[6],
-    COLUMNS[7],
-    COLUMNS[0],
-    COLUMNS[1],
-    COLUMNS[2],
-    COLUMNS[3],
-    COLUMNS[4],
-    COLUMNS[5],
-    COLUMNS[6],
-    COLUMNS[7],
-];
\ No newline at end of file
+                let Some(m) = random.pick_move(&board) else { break }; // Game over
+                let before = board;
+                let flips = play_move(&mut board, m);
+                let after = board;
+                unplay_move(&mut board, m, flips);
+                assert_eq!(board, before);
+
+                board = after;
+            }
+        }
+    }
+}
@@ -1,7 +1,13 @@
+//! The board and the rules of othello
+
 mod play;
 mod validation;
 mod board;
+mod zobrist;
+mod notation;
 
-pub use play::{play, unplay};
-pub use validation::validate;
-pub use board::{Board, ROW_LOOKUP, COLUMN_LOOKUP, NEGATIVE_DIAGONAL_LOOKUP, POSITIVE_DIAGONAL_LOOKUP};
\ No newline at end of file
+pub use play::{play, unplay, play_move, unplay_move, Move};
+pub use validation::{validate, validate_move, flips, bitboard_flips};
+pub use board::{Board, Bits, DIRECTIONS, shift};
+pub use zobrist::{PIECE_KEYS, SIDE_KEY};
+pub use notation::{to_index, to_notation, parse_notation};
@@ -0,0 +1,35 @@
+/// Converts human row and column to index on main board
+pub fn to_index(row: usize, column: char) -> usize {
+    (column.to_ascii_lowercase() as u32 - 97) as usize + (8 - row) * 8
+}
+
+/// Converts index on main board to human row and column
+pub fn to_notation(i: usize) -> (usize, char) {
+    (8 - (i / 8), std::char::from_u32(i as u32 % 8 + 97)
+        .unwrap()
+        .to_ascii_uppercase())
+}
+
+/// Parses a tile typed by a human, column and row can be given in either
+/// order and column in either case, so "d3", "D3" and "3d" are all D3.
+/// Whitespace around the tile is ignored.
+/// 
+/// Returns index on main board, None if the text is not a tile on the board
+pub fn parse_notation(input: &str) -> Option<usize> {
+    let chars: Vec<char> = input.trim().chars().collect();
+
+    let (column, row) = match chars[..] {
+        [column, row] if column.is_ascii_alphabetic() => (column, row),
+        [row, column] if column.is_ascii_alphabetic() => (column, row),
+        _ => return None
+    };
+
+    let column = column.to_ascii_lowercase();
+    let row = row.to_digit(10)? as usize;
+
+    if !('a'..='h').contains(&column) || !(1..=8).contains(&row) {
+        return None
+    }
+
+    Some(to_index(row, column))
+}
@@ -1,10 +1,14 @@
-use crate::game::{
-    Board, 
-    ROW_LOOKUP, 
-    COLUMN_LOOKUP, 
-    POSITIVE_DIAGONAL_LOOKUP, 
-    NEGATIVE_DIAGONAL_LOOKUP
-};
+use crate::game::{Board, Bits, flips};
+
+/// A move of one side, either a piece placed on an index of the
+/// main board, or a pass when that side has no valid moves
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Move {
+    /// Piece placed on index of main board
+    Play(usize),
+    /// Turn is handed over without placing a piece
+    Pass
+}
 
 /// Function to play moves onto board
 /// 
@@ -15,111 +19,12 @@ use crate::game::{
 /// 
 /// Returns vector of pieces flipped, very last element is inserted piece
 pub fn play(board: &mut Board, i: usize, color: bool) -> Vec<usize> {
-    board.insert(i, color); // Adds piece onto board
-    board.prev_color = color;
-
-    let axis = [
-        POSITIVE_DIAGONAL_LOOKUP[i],
-        NEGATIVE_DIAGONAL_LOOKUP[i], 
-        ROW_LOOKUP[i], 
-        COLUMN_LOOKUP[i]
-    ];
-
-    let mut flips = vec![];
-    for a in axis {
-        let index = a.iter().position(|&pos| pos == i).unwrap(); // Should never be able to reach None case, want it to panic if it does
-        let mut temp_flips = vec![];
-        let mut sequences = [Vec::from(&a[..index]), Vec::from(&a[(index + 1)..])];
-        sequences[0].reverse();
-
-        for sequence in sequences {
-            for &pos in &sequence {
-                if board.color_pieces[&color].contains(&pos) { // End of flip sequence
-                    break;
-    
-                } else if board.pieces[pos] == None || // Gap in sequence means end of sequence
-                        pos == *sequence.last().unwrap() && // Sequence did not terminate
-                        board.color_pieces[&!color].contains(&pos) { 
-                    
-                    temp_flips = vec![];
-                    break;
-
-                } else if board.color_pieces[&!color].contains(&pos) { // Valid tile to flip
-                    temp_flips.push(pos)
-                }
-            }
-
-            flips.extend(temp_flips.iter());
-            temp_flips = vec![];
-        }
-    }
-
-    for &flip in &flips { // Carryout flips
-        board.flip(flip) 
-    }
-
-    flips.push(i);
-    flips
-
-}
-
-// Untested *potentially* faster, 
-// And *potentially* broken
-#[allow(dead_code)]
-pub fn play2(board: &mut Board, i: usize, color: bool) -> Vec<usize> {
-    board.prev_color = color;
-    board.insert(i, color);
-    let axis = [COLUMN_LOOKUP[i], ROW_LOOKUP[i], NEGATIVE_DIAGONAL_LOOKUP[i], POSITIVE_DIAGONAL_LOOKUP[i]];
-    let mut flips = vec![];
-
-    for ax in axis {
-        let index = ax.iter().position(|&pos| pos == i).unwrap(); // Cannot be None
-        let axis_length = ax.iter().len();
+    let mask = flips(board, i, color); // Found before insert, insert doesn't change it
 
-        match axis_length {
-            1 => continue, // No flips possible on this axis
-            2 => continue, // It is impossible for there to be a piece on the other side
-            3 => if index == 1 { continue }, // If it is in the middle No flips are possible
-            _ => {}
-        }
-
-        if index > 1 { // If it is second from edge there is no point in searching that direction 
-            if let Some(c) = board.pieces[ax[index - 1]] {
-                if c != color { // Adjacent piece must be opposite color
-                    for pos in (0..=(index - 2)).rev() {
-                        match board.pieces[ax[pos]] {
-                            Some(c) => if c == color {
-                                break; // terminate sequence, after flips found
-                            } else {
-                                flips.push(ax[pos])
-                            },
-                            None => break // Piece in sequence can't be empty
-                        }
-                    }
-                }
-            }
-        }
-
-        // index of last element is (axis_length - 1)
-        if index < (axis_length - 2) { // If it is second from edge there is no point in searching that direction 
-            if let Some(piece) = board.pieces[ax[index + 1]] {
-                if piece != color { // Adjacent piece must be opposite color
-                    for pos in (index + 2)..axis_length {
-                        match board.pieces[ax[pos]] {
-                            Some(c) => if c == color {
-                                break; // terminate sequence, after flips found
-                            } else {
-                                flips.push(ax[pos])
-                            },
-                            None => break // Piece in sequence can't be empty
-                        }
-                    }
-                }
-            }
-            
-        }
-    }
+    board.insert(i, color); // Adds piece onto board
+    board.to_move = !color;
 
+    let mut flips: Vec<usize> = Bits(mask).collect();
     for &flip in &flips { // Carryout flips
         board.flip(flip) 
     }
@@ -140,12 +45,45 @@ pub fn play2(board: &mut Board, i: usize, color: bool) -> Vec<usize> {
 /// *   flips: Vector of piece indices that were flipped
 pub fn unplay(board: &mut Board, color: bool, mut flips: Vec<usize>) {
     board.remove(flips.pop().unwrap(), color); // Remove inserted piece
-    board.prev_color = !color; 
-    // Might cause bug if two moves are played by one side in case of no moves being found
-    // https://en.wikipedia.org/wiki/Ostrich_algorithm
+    board.to_move = color; // Side that played is to move again, even if it played twice in a row
 
     for i in flips {
         board.flip(i) // Re-flip flipped tiles
     }
 }
 
+/// Passes the turn to the other side, nothing on the board changes.
+/// Passing twice undoes a pass
+pub fn pass(board: &mut Board) {
+    board.to_move = !board.to_move;
+}
+
+/// Plays a move for the side to move
+/// 
+/// # Arguments
+/// *   board: Pointer to board which move will be played on
+/// *   m: Move to play
+/// 
+/// Returns vector of pieces flipped like play, empty for a pass
+pub fn play_move(board: &mut Board, m: Move) -> Vec<usize> {
+    match m {
+        Move::Play(i) => play(board, i, board.to_move),
+        Move::Pass => {
+            pass(board);
+            vec![]
+        }
+    }
+}
+
+/// Function to undo play_move function, same rules as unplay apply
+/// 
+/// # Arguments
+/// *   board: Pointer to board where move will be undone
+/// *   m: Move that was played
+/// *   flips: Vector returned by play_move
+pub fn unplay_move(board: &mut Board, m: Move, flips: Vec<usize>) {
+    match m {
+        Move::Play(_) => unplay(board, !board.to_move, flips),
+        Move::Pass => pass(board)
+    }
+}
@@ -1,85 +1,70 @@
-use super::{
-    Board, 
-    play, 
-    COLUMN_LOOKUP, 
-    ROW_LOOKUP, 
-    NEGATIVE_DIAGONAL_LOOKUP, 
-    POSITIVE_DIAGONAL_LOOKUP
-};
+use super::{Board, Move, DIRECTIONS, shift};
+use crate::bot::moves::move_mask;
 
-#[allow(dead_code)]
-pub fn validate_unoptimized(board: &Board, i: usize, color: bool) -> bool {
-    let mut board = board.clone();
+/// Finds every piece that would be flipped by a move. Walks outward from
+/// the move in all 8 directions at once with shifts, a line of opponent
+/// pieces only counts if it is closed off by a piece of the moving color.
+/// The board is not and should not be modified by this function EVER
+///
+/// # Arguments
+/// *   board: Board which is used to find flips
+/// *   i: Index of move on main board
+/// *   color: Color of piece to be inserted
+///
+/// Returns bitmap of flipped pieces, empty if move is invalid
+pub fn flips(board: &Board, i: usize, color: bool) -> u64 {
+    bitboard_flips(board.bitboard(color), board.bitboard(!color), i)
+}
+
+/// flips function but bitmaps of both sides are inputted, for code
+/// that keeps its own bitmaps instead of a Board
+///
+/// # Arguments
+/// *   player: Bitmap of side making the move
+/// *   opponent: Bitmap of other side
+/// *   i: Index of move on main board
+pub fn bitboard_flips(player: u64, opponent: u64, i: usize) -> u64 {
+    let mut flips = 0;
+    for direction in DIRECTIONS {
+        let mut line = 0;
+        let mut tile = shift(1 << i, direction);
 
-    match board.pieces.get(i) {
-        Some(c) => if c.is_some() {
-            return false; // Tile already full
-        },
-        None => return false // Out of bounds
+        while tile & opponent != 0 { // Collect run of opponent pieces
+            line |= tile;
+            tile = shift(tile, direction);
+        }
+
+        if tile & player != 0 { // Run was closed off, it gets flipped
+            flips |= line;
+        }
     }
 
-    let piece_count = board.piece_count[&color];
-    play(&mut board, i, color);
-    board.piece_count[&color] > piece_count + 1
+    flips
 }
 
-// Much faster version
-
-/// Move validation function, runs until at least 1 valid flip is found,
-/// essentially the play function with preliminary checks and stops at first
-/// found valid flip. The board is not and should not be modified by this 
-/// function EVER, for the evaluation to be accurate
-/// 
+/// Move validation function, a move is valid when it lands on an empty
+/// tile and flips at least 1 piece.
+///
 /// # Arguments
 /// *   board: Board which is used to validate a move
 /// *   i: Index of move on main board
 /// *   color: Color of piece to be inserted
 pub fn validate(board: &Board, i: usize, color: bool) -> bool {
-    if i > 63 || board.pieces[i].is_some() { return false } // Out of bounds / Location full
-
-    let axis = [COLUMN_LOOKUP[i], ROW_LOOKUP[i], NEGATIVE_DIAGONAL_LOOKUP[i], POSITIVE_DIAGONAL_LOOKUP[i]];
-    for ax in axis {
-        let index = ax.iter().position(|&pos| pos == i).unwrap(); // Cannot be None
-        let axis_length = ax.iter().len();
+    if i > 63 || board.occupied() >> i & 1 == 1 { return false } // Out of bounds / Location full
 
-        match axis_length {
-            1 => continue, // No flips possible on this axis
-            2 => continue, // It is impossible for there to be a piece on the other side
-            3 => if index == 1 { continue }, // If it is in the middle No flips are possible
-            _ => {}
-        }
-
-        if index > 1 { // If it is second from edge there is no point in searching that direction 
-            if let Some(c) = board.pieces[ax[index - 1]] {
-                if c != color { // Adjacent piece must be opposite color
-                    for pos in (0..=(index - 2)).rev() {
-                        match board.pieces[ax[pos]] {
-                            Some(c) => if c == color {
-                                return true // terminated sequence possible, move possible
-                            }, // Nothing happens if the piece is of the opposite color
-                            None => break // Piece in sequence can't be empty
-                        }
-                    }
-                }
-            }
-        }
+    flips(board, i, color) != 0
+}
 
-        // index of last element is (axis_length - 1)
-        if index < (axis_length - 2) { // If it is second from edge there is no point in searching that direction 
-            if let Some(piece) = board.pieces[ax[index + 1]] {
-                if piece != color { // Adjacent piece must be opposite color
-                    for pos in (index + 2)..axis_length {
-                        match board.pieces[ax[pos]] {
-                            Some(c) => if c == color {
-                                return true // terminated sequence possible, move possible
-                            }, // Nothing happens if the piece is of the opposite color
-                            None => break // Piece in sequence can't be empty
-                        }
-                    }
-                }
-            }
-        }
+/// validate function for any Move of the side to move. A pass
+/// is only valid when the side to move has no other move and
+/// the game is not over
+///
+/// # Arguments
+/// *   board: Board which is used to validate a move
+/// *   m: Move to be played
+pub fn validate_move(board: &Board, m: Move) -> bool {
+    match m {
+        Move::Play(i) => validate(board, i, board.to_move),
+        Move::Pass => move_mask(board, board.to_move) == 0 && move_mask(board, !board.to_move) != 0
     }
-
-    false
 }
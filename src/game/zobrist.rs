@@ -0,0 +1,34 @@
+// **** Random keys used to hash board states ****
+// Every piece on every tile gets its own key, the hash of a board is
+// all keys of the pieces on it XORed together. XOR is its own inverse,
+// so the hash is updated as pieces are inserted, removed and flipped
+// instead of being recomputed from scratch.
+
+/// Keys for every tile, indexed by [color as usize][index]
+pub const PIECE_KEYS: [[u64; 64]; 2] = generate_keys();
+
+/// Key XORed in when it is white's turn to move
+pub const SIDE_KEY: u64 = splitmix(u64::MAX);
+
+/// Small pseudo random number generator, good enough for hash keys
+/// and usable at compile time so the keys never change between runs
+const fn splitmix(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+const fn generate_keys() -> [[u64; 64]; 2] {
+    let mut keys = [[0; 64]; 2];
+    let mut seed = 0;
+
+    let mut i = 0;
+    while i < 128 { // for loops are not allowed in const fn
+        seed = splitmix(seed);
+        keys[i / 64][i % 64] = seed;
+        i += 1;
+    }
+
+    keys
+}
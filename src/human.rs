@@ -1,86 +1,119 @@
 use std::io::Write;
 
-use crate::game;
-use crate::game::{Board, validate};
-use crate::bot::moves::generate_moves;
-use text_io::scan;
+use othellotron::game::{Board, Move, parse_notation};
 
-/// Human interface function. Combines user input, parsing and playing.
-/// Unfortunately, the current state of this function only supports,
-/// invalid moves written in the right way, when text_io::scan!() 
-/// attempts to parse the wrong type it panics, the convenience of the 
-/// library to me the programmer has made it the reason I have chosen to use it.
-/// I apologize in advance to whoever is interested enough to read the code 
-/// for this project and has likely chosen to ATTEMPT to beat the bot, if you 
-/// have found yourself losing to the bot and the bot crashing due to a miss input.
-/// If you enter an invalid move it will let you re-enter a valid move, 
-/// thus if you are not happy with the row you typed in make the column 
-/// invalid to re-enter another move
-/// 
-/// # Arguments
-/// *   board: State of board
-/// *   color: Color of bot
-/// *   max_depth: Depth that is searched (Will switch to Iterative Deepening in the future)
-#[allow(unused_must_use)] // To not get warnings from "std::io::stdout().flush()"
-pub fn play(board: &mut Board, color: bool) {
-    let row: usize; // Define type for row
-    let column: char; // Define type for column
-
-    if generate_moves(board, color).is_empty() {
-        if generate_moves(board, !color).is_empty() { // Game Over
-            match board.piece_count[&!color].cmp(&board.piece_count[&color]) {
-                std::cmp::Ordering::Greater => println!("You Won! Piece Ratio = {}:{} (You:Bot)", board.piece_count[&!color], board.piece_count[&color]),
-                std::cmp::Ordering::Equal => println!("B It's a tie! Piece Ratio = {}:{} (You:Bot)", board.piece_count[&!color], board.piece_count[&color]),
-                std::cmp::Ordering::Less => println!("You Lost! Piece Ratio = {}:{} (You:Bot)", board.piece_count[&!color], board.piece_count[&color]),
-            }
-            std::process::exit(0); // Exit program
-        } else {
-            return
-        }
-    }
+/// Everything a human can type at the prompt
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Play(Move), // Tile like "D3", or "pass"
+    Undo,
+    Redo,
+    Hint,
+    Explain,
+    New,
+    Swap,
+    Depth(u32),
+    Time(u64),
+    ShowMoves,
+    Save(String),
+    Load(String),
+    Help,
+    Quit
+}
 
-    // **** Taking user input to parse move into something that the crate::game::play function can use ****
+/// Text printed by the help command
+pub const HELP: &str = "\
+Commands:
+  D3 / 3d       Play a move, column and row in either order
+  pass          Pass, only allowed when you have no moves
+  undo          Take back your last move and the bot's reply
+  redo          Play a taken back move again
+  hint          Show the move the bot would play for you
+  explain       Show how the bot scores this position and the line it expects
+  show moves    List your valid moves
+  new           Start a new game
+  swap          Switch colors with the bot
+  depth N       Bot searches N plies deep
+  time N        Bot thinks for N milliseconds per move
+  save FILE     Save the moves of this game to a file
+  load FILE     Load the moves of a game from a file
+  help          Show this text
+  quit          Exit the program";
 
-    print!("Enter Row (Number): ");
-    std::io::stdout().flush();
-    scan!("{}", row); 
-    // TODO: write standard library implementation for this to 
-    // TODO-continuation: avoid panicking and losing progress in a game.
+/// Parses one line typed at the prompt. Anything that is not a command
+/// is read as a move, the same way as a move typed on its own.
+///
+/// Returns the command, or a message saying why the line was not understood
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let lowercase: Vec<String> = words.iter().map(|word| word.to_ascii_lowercase()).collect();
+    let lowercase: Vec<&str> = lowercase.iter().map(String::as_str).collect();
 
-    if row > 8 {
-        println!("Invalid Row, try again.");
-        return play(board, color)
+    match lowercase[..] {
+        ["pass"] => Ok(Command::Play(Move::Pass)),
+        ["undo"] => Ok(Command::Undo),
+        ["redo"] => Ok(Command::Redo),
+        ["hint"] => Ok(Command::Hint),
+        ["explain"] => Ok(Command::Explain),
+        ["new"] => Ok(Command::New),
+        ["swap"] => Ok(Command::Swap),
+        ["show", "moves"] => Ok(Command::ShowMoves),
+        ["help"] => Ok(Command::Help),
+        ["quit"] | ["exit"] => Ok(Command::Quit),
+        ["depth", n] => match n.parse() {
+            Ok(depth) if depth > 0 => Ok(Command::Depth(depth)),
+            _ => Err(format!("\"{n}\" is not a depth, enter a whole number above 0"))
+        },
+        ["time", n] => match n.parse() {
+            Ok(time) if time > 0 => Ok(Command::Time(time)),
+            _ => Err(format!("\"{n}\" is not a time, enter a whole number of milliseconds above 0"))
+        },
+        ["save", _] => Ok(Command::Save(words[1].to_string())), // File names keep their case
+        ["load", _] => Ok(Command::Load(words[1].to_string())),
+        [word] => match parse_notation(word) {
+            Some(i) => Ok(Command::Play(Move::Play(i))),
+            None => Err(format!("\"{word}\" is not a tile or command, enter a column A-H and a row 1-8 or \"help\""))
+        },
+        _ => Err(format!("\"{}\" is not a command, enter \"help\" to see all commands", line.trim()))
     }
+}
 
-    print!("Enter Column (Letter): ");
-    std::io::stdout().flush();
-    scan!("{}", column); // TODO: See previous scan!() call, roughly line 46
-
+/// Prompts for and reads one line of input
+///
+/// Returns line without trailing newline, None at end of input or if input can't be read
+pub fn read_line(prompt: &str) -> Option<String> {
+    print!("{prompt}");
+    std::io::stdout().flush().ok();
 
-    if (column.to_ascii_lowercase() as u32) < 97 || (column.to_ascii_lowercase() as u32) > 104 {
-        println!("Invalid Column, try again.");
-        return play(board, color)
-    }
-
-    let i = to_index(row, column);
-    
-    if validate(board, i, color) { // Validates that move that was entered is actually valid
-        game::play(board, i, color);
-    } else {
-        println!("Invalid move");
-        play(board, color) // Next attempt at entering a valid move
+    let mut line = String::new();
+    match std::io::stdin().read_line(&mut line) {
+        Ok(0) => None, // EOF
+        Ok(_) => Some(line.trim_end().to_string()),
+        Err(e) => {
+            eprintln!("Could not read input: {e}");
+            None
+        }
     }
-
 }
 
-/// Converts human row and column to index on main board
-pub fn to_index(row: usize, column: char) -> usize {
-    (column.to_ascii_lowercase() as u32 - 97) as usize + (8 - row) * 8
-}
+/// Prints who won, from the human's side if there is only one human
+///
+/// # Arguments
+/// *   board: Final state of board
+/// *   human: Color of human, None if both or neither side is human
+pub fn game_over(board: &Board, human: Option<bool>) {
+    let (white, black) = (board.piece_count(true), board.piece_count(false));
 
-/// Converts index on main board to human row and column
-pub fn to_notation(i: usize) -> (usize, char) {
-    (8 - (i / 8), std::char::from_u32(i as u32 % 8 + 97)
-        .unwrap()
-        .to_ascii_uppercase())
+    match human {
+        Some(color) => match board.piece_count(color).cmp(&board.piece_count(!color)) {
+            std::cmp::Ordering::Greater => println!("You Won! Piece Ratio = {}:{} (You:Bot)", board.piece_count(color), board.piece_count(!color)),
+            std::cmp::Ordering::Equal => println!("It's a tie! Piece Ratio = {}:{} (You:Bot)", board.piece_count(color), board.piece_count(!color)),
+            std::cmp::Ordering::Less => println!("You Lost! Piece Ratio = {}:{} (You:Bot)", board.piece_count(color), board.piece_count(!color)),
+        },
+        None => match white.cmp(&black) {
+            std::cmp::Ordering::Greater => println!("White Won! Piece Ratio = {white}:{black} (White:Black)"),
+            std::cmp::Ordering::Equal => println!("It's a tie! Piece Ratio = {white}:{black} (White:Black)"),
+            std::cmp::Ordering::Less => println!("Black Won! Piece Ratio = {white}:{black} (White:Black)"),
+        }
+    }
 }
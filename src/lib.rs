@@ -0,0 +1,28 @@
+//! Othellotron, a home baked Othello engine.
+//!
+//! The engine is split in two modules:
+//! *   [`game`]: The board and the rules, playing and un-playing moves
+//! *   [`bot`]: Move generation, evaluation and search
+//!
+//! The board is stored as bitmaps, index 0 is A8 and index 63 is H1.
+//! Colors are booleans, `true` is white and `false` is black, white moves first.
+//!
+//! ```
+//! use othellotron::game::{Board, Move, play_move};
+//! use othellotron::bot::{self, Config, Limit, TranspositionTable};
+//!
+//! let mut board = Board::new();
+//! let config = Config { limit: Limit::Depth(3), ..Config::default() };
+//! let tt = TranspositionTable::default(); // Kept for every move of the game
+//!
+//! if let Some(Move::Play(i)) = bot::choose_move(&board, board.to_move, &config, &tt) {
+//!     let score = bot::evaluate(&board, board.to_move);
+//!     play_move(&mut board, Move::Play(i));
+//!     println!("{i} was played from a position scored {score}\n{board}");
+//! }
+//! ```
+
+#![warn(missing_docs)]
+
+pub mod bot;
+pub mod game;
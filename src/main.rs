@@ -1,24 +1,20 @@
-mod bot;
-mod game;
+mod cli;
 mod human;
-
-use crate::game::Board;
+mod repl;
 
 fn main() {
-    let mut board = Board::new();
-    println!("{board}");
-    
-    // let mut i = 0;
-    // loop {
-    //     println!("Ply {}: {}", i, crate::bot::moves::_ply_move_counter_test(&board, i, true));
-    //     i += 1;
-    // }
-
-    loop {
-        human::play(&mut board, true);
-        println!("{board}");
-        bot::play(&mut board, false, 7);
-        println!("{board}");
+    match cli::parse_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => match repl::Session::new(options) {
+            Ok(mut session) => session.run(),
+            Err(message) => {
+                eprintln!("{message}");
+                std::process::exit(1);
+            }
+        },
+        Ok(None) => println!("{}", cli::USAGE),
+        Err(message) => {
+            eprintln!("{message}\n\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
     }
-
 }
@@ -0,0 +1,394 @@
+use std::fs;
+
+use othellotron::bot::{self, Budget, Config, Evaluator, Heuristic, Limit, PatternEvaluator, Random, TranspositionTable};
+use othellotron::bot::moves::generate_moves;
+use othellotron::game::{Board, Move, play_move, validate_move, parse_notation, to_notation};
+
+use crate::cli::{Eval, Options, Output, Player};
+use crate::human::{self, Command, HELP};
+
+/// Board before each move and the move played on it
+type History = Vec<(Board, Move)>;
+
+/// Game at the terminal, between a human and the bot, two humans or two
+/// bots. Every board before a move is kept, so that moves can be taken
+/// back and played again without having to undo flips.
+pub struct Session {
+    board: Board,
+    start: Board, // Board the game started from, new games start here too
+    history: History,
+    undone: Vec<Move>, // Moves taken back by undo, most recently taken back last
+    white: Player,
+    black: Player,
+    config: Config,
+    evaluator: Box<dyn Evaluator>,
+    tt: TranspositionTable, // Kept for the whole session, what one move learns helps the next
+    output: Output
+}
+
+impl Session {
+    /// Sets up the game, random moves are played from the
+    /// starting position straight away
+    ///
+    /// Returns session, or a message if the pattern weights could not be read
+    /// or Multi-ProbCut is asked for with an evaluation it has no parameters for
+    pub fn new(options: Options) -> Result<Self, String> {
+        let evaluator: Box<dyn Evaluator> = match (options.eval, &options.weights) {
+            (Eval::Heuristic, _) => Box::new(Heuristic { phases: options.phases, ..Heuristic::default() }),
+            (Eval::Pattern, None) => Box::new(PatternEvaluator::new()),
+            (Eval::Pattern, Some(file)) => Box::new(PatternEvaluator::load(file)
+                .map_err(|e| format!("Could not load weights from {file}: {e}"))?)
+        };
+
+        if options.config.probcut && evaluator.cuts().is_none() {
+            return Err(String::from("Multi-ProbCut has only been fitted for the heuristic evaluation with its default phases"))
+        }
+
+        if options.output == Output::Plain {
+            colored::control::set_override(false);
+        }
+
+        let mut start = options.position;
+        let mut random = Random::new(options.seed);
+        for _ in 0..options.random_moves {
+            if random.play_move(&mut start).is_none() {
+                break // Game Over
+            }
+        }
+
+        Ok(Self {
+            board: start,
+            start,
+            history: vec![],
+            undone: vec![],
+            white: options.white,
+            black: options.black,
+            config: options.config,
+            evaluator,
+            tt: TranspositionTable::default(),
+            output: options.output
+        })
+    }
+
+    /// Runs the game until a human quits or input runs out, or until the
+    /// game is over if only bots are playing. A bot moves whenever it is
+    /// its turn, otherwise a line is read and run as a move or command.
+    pub fn run(&mut self) {
+        self.show();
+
+        loop {
+            if self.game_is_over() && self.white == Player::Bot && self.black == Player::Bot {
+                return
+            }
+
+            if self.player(self.board.to_move) == Player::Bot && !self.game_is_over() {
+                self.bot_move();
+                continue
+            }
+
+            let line = match human::read_line("Enter Move or Command (\"help\" for commands): ") {
+                Some(line) => line,
+                None => return println!("\nNo more input, ending game.")
+            };
+
+            if line.trim().is_empty() {
+                continue
+            }
+
+            let result = match human::parse_command(&line) {
+                Ok(Command::Quit) => return,
+                Ok(command) => self.execute(command),
+                Err(message) => Err(message)
+            };
+
+            if let Err(message) = result {
+                println!("{message}");
+            }
+        }
+    }
+
+    /// Runs one command, returns a message if it could not be run
+    fn execute(&mut self, command: Command) -> Result<(), String> {
+        match command {
+            Command::Play(m) => {
+                if self.game_is_over() {
+                    return Err(String::from("The game is over, enter \"new\" to play again or \"undo\" to go back."))
+                }
+
+                if !validate_move(&self.board, m) {
+                    return Err(match m {
+                        Move::Pass => String::from("You have moves, you can't pass."),
+                        Move::Play(_) => String::from("Invalid move, try again.")
+                    })
+                }
+
+                self.undone.clear(); // New move, the taken back line is gone
+                self.play(m);
+                self.show();
+            },
+
+            Command::Undo => {
+                // Takes back the bot's replies and the human's last move, so that it is the human's turn again
+                if !self.history.iter().any(|(board, _)| self.player(board.to_move) == Player::Human) {
+                    return Err(String::from("Nothing to undo."))
+                }
+
+                while let Some((board, m)) = self.history.pop() {
+                    self.board = board;
+                    self.undone.push(m);
+
+                    if self.player(board.to_move) == Player::Human {
+                        break
+                    }
+                }
+
+                self.show();
+            },
+
+            Command::Redo => {
+                if self.undone.is_empty() {
+                    return Err(String::from("Nothing to redo."))
+                }
+
+                // Plays the human's move and the bot's replies that were taken back with it
+                while let Some(m) = self.undone.pop() {
+                    self.play(m);
+
+                    if self.player(self.board.to_move) == Player::Human {
+                        break
+                    }
+                }
+
+                self.show();
+            },
+
+            Command::Hint => match bot::choose_move_with(&self.board, self.board.to_move, &self.config, &*self.evaluator, &self.tt) {
+                Some(m) => println!("Hint: {}", notation(m)),
+                None => return Err(String::from("The game is over, there are no moves."))
+            },
+
+            Command::Explain => self.explain()?,
+
+            Command::ShowMoves => {
+                let moves: Vec<String> = generate_moves(&self.board, self.board.to_move)
+                    .into_iter()
+                    .map(|i| notation(Move::Play(i)))
+                    .collect();
+
+                if moves.is_empty() {
+                    println!("No moves.");
+                } else {
+                    println!("Moves: {}", moves.join(" "));
+                }
+            },
+
+            Command::New => {
+                self.board = self.start;
+                self.history.clear();
+                self.undone.clear();
+                self.show();
+            },
+
+            Command::Swap => {
+                std::mem::swap(&mut self.white, &mut self.black);
+                self.undone.clear(); // Turns of the taken back line no longer line up
+                println!("White is now played by {:?}, Black by {:?}.", self.white, self.black);
+            },
+
+            Command::Depth(depth) => {
+                self.config.limit = Limit::Depth(depth);
+                println!("Bot searches {depth} plies deep.");
+            },
+
+            Command::Time(time) => {
+                self.config.limit = Limit::Time(time);
+                self.config.mcts.budget = Budget::Time(time);
+                println!("Bot thinks for {time}ms per move.");
+            },
+
+            Command::Save(file) => {
+                let moves: Vec<String> = self.history.iter().map(|&(_, m)| notation(m)).collect();
+
+                // Starting position is only written when it is not the normal one
+                let mut text = if self.start == Board::new() { String::new() } else { self.start.position() + "\n" };
+                text += &(moves.join(" ") + "\n");
+
+                fs::write(&file, text)
+                    .map_err(|e| format!("Could not save to {file}: {e}"))?;
+                println!("Saved {} moves to {file}.", moves.len());
+            },
+
+            Command::Load(file) => {
+                let text = fs::read_to_string(&file)
+                    .map_err(|e| format!("Could not load {file}: {e}"))?;
+                let (start, board, history) = replay(&text).map_err(|e| format!("Could not load {file}: {e}"))?;
+
+                self.start = start;
+                self.board = board;
+                self.history = history;
+                self.undone.clear();
+                self.show();
+            },
+
+            Command::Help => println!("{HELP}"),
+
+            Command::Quit => {}
+        }
+
+        Ok(())
+    }
+
+    /// Lets the bot pick and play a move for the side to move
+    fn bot_move(&mut self) {
+        let color = self.board.to_move;
+
+        if let Some(m) = bot::choose_move_with(&self.board, color, &self.config, &*self.evaluator, &self.tt) {
+            self.play(m);
+            println!("Bot ({}) plays {}", if color { "White" } else { "Black" }, notation(m));
+            self.show();
+        }
+    }
+
+    /// Prints the score of every term of the evaluation for the board,
+    /// and for the end of the line the bot expects to be played from it
+    fn explain(&self) -> Result<(), String> {
+        if self.game_is_over() {
+            return Err(String::from("The game is over, there is nothing to explain."))
+        }
+
+        let color = self.board.to_move;
+        let (side, other) = if color { ("White", "Black") } else { ("Black", "White") };
+        let now = self.evaluator.breakdown(&self.board, color)
+            .ok_or(String::from("This evaluation has no terms to show."))?;
+
+        // The line is searched like a normal move, the board at its end is what the score comes from.
+        // The search scores that board for the side to move there, turned around if that is the opponent
+        let analysis = bot::analyze(&self.board, color, &self.config, &*self.evaluator, &self.tt);
+        let end = analysis.as_ref().map(|analysis| {
+            let mut end = self.board;
+            for &m in &analysis.pv {
+                play_move(&mut end, m);
+            }
+
+            let mut breakdown = self.evaluator.breakdown(&end, end.to_move);
+            if end.to_move != color {
+                for (_, score) in breakdown.iter_mut().flat_map(|breakdown| breakdown.terms.iter_mut()) {
+                    *score = -*score;
+                }
+            }
+            (end, breakdown)
+        });
+
+        println!("Evaluation for {side}, higher is better for {side}:");
+        println!("  {:<24}{:>8}{:>14}", "Term", "Now", "End of line");
+        for (n, &(term, score)) in now.terms.iter().enumerate() {
+            let at_end = match &end {
+                Some((_, Some(breakdown))) => breakdown.terms[n].1.to_string(),
+                _ => String::from("-")
+            };
+            println!("  {term:<24}{score:>8}{at_end:>14}");
+        }
+        let total = match &end {
+            Some((_, Some(breakdown))) => breakdown.total().to_string(),
+            _ => String::from("-")
+        };
+        println!("  {:<24}{:>8}{total:>14}", "Total", now.total());
+
+        match (analysis, end) {
+            (Some(analysis), Some((end, breakdown))) => {
+                let line: Vec<String> = analysis.pv.iter().map(|&m| notation(m)).collect();
+                println!("Bot's line: {} (depth {}, score {})", line.join(" "), analysis.depth, analysis.score);
+
+                if breakdown.is_none() {
+                    println!("The line ends the game, Piece Ratio = {}:{} ({side}:{other})", end.piece_count(color), end.piece_count(!color));
+                }
+            },
+            _ => println!("{side} has no moves, so there is no line to show.")
+        }
+
+        Ok(())
+    }
+
+    fn player(&self, color: bool) -> Player {
+        if color { self.white } else { self.black }
+    }
+
+    /// Color of the only human, None if both or neither side is human
+    fn human(&self) -> Option<bool> {
+        match (self.white, self.black) {
+            (Player::Human, Player::Bot) => Some(true),
+            (Player::Bot, Player::Human) => Some(false),
+            _ => None
+        }
+    }
+
+    fn play(&mut self, m: Move) {
+        self.history.push((self.board, m));
+        play_move(&mut self.board, m);
+    }
+
+    fn game_is_over(&self) -> bool {
+        generate_moves(&self.board, true).is_empty() && generate_moves(&self.board, false).is_empty()
+    }
+
+    /// Prints the board, with the result if the game is over
+    fn show(&self) {
+        if self.output != Output::Quiet {
+            println!("{}", self.board);
+        }
+
+        let color = self.board.to_move;
+        if self.game_is_over() {
+            human::game_over(&self.board, self.human());
+        } else if self.player(color) == Player::Human && generate_moves(&self.board, color).is_empty() {
+            println!("{} has no moves, enter \"pass\".", if color { "White" } else { "Black" });
+        }
+    }
+}
+
+/// Plays out a saved list of moves, from the position at the start
+/// of the text if there is one, otherwise from the normal starting position
+///
+/// Returns starting board, final board and the board before each move with the move,
+/// or which move was not valid
+fn replay(text: &str) -> Result<(Board, Board, History), String> {
+    let mut words = text.split_whitespace().peekable();
+    let start = match words.peek().and_then(|word| word.parse::<Board>().ok()) {
+        Some(start) => {
+            words.next();
+            start
+        },
+        None => Board::new()
+    };
+
+    let mut board = start;
+    let mut history = vec![];
+
+    for (n, word) in words.enumerate() {
+        let m = if word.eq_ignore_ascii_case("pass") {
+            Move::Pass
+        } else {
+            Move::Play(parse_notation(word).ok_or(format!("move {} \"{word}\" is not a tile", n + 1))?)
+        };
+
+        if !validate_move(&board, m) {
+            return Err(format!("move {} \"{word}\" is not valid", n + 1))
+        }
+
+        history.push((board, m));
+        play_move(&mut board, m);
+    }
+
+    Ok((start, board, history))
+}
+
+/// Writes a move the way a human would type it, like "D3" or "pass"
+fn notation(m: Move) -> String {
+    match m {
+        Move::Play(i) => {
+            let (row, column) = to_notation(i);
+            format!("{column}{row}")
+        },
+        Move::Pass => String::from("pass")
+    }
+}